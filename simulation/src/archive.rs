@@ -0,0 +1,78 @@
+use serde_json::{json, Value};
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+
+use crate::brain::BrainNetwork;
+use crate::species::{species_id, SPECIES_NAMES};
+
+// Writes the best genomes of every species at regular timesteps, one JSON object per line
+pub struct GenomeArchive {
+    file: Option<File>,
+    // Timesteps between two snapshots, 0 turns the archive off
+    pub interval: u64,
+    // Number of top agents stored from every species
+    pub count: usize,
+}
+
+impl GenomeArchive {
+    pub fn new(config: &Value, filename: Option<&str>) -> Self {
+        let interval = config["genome_archive_interval"].as_u64().unwrap() as u64;
+        let file = match filename {
+            Some(filename) if interval > 0 => Some(File::create(filename).unwrap()),
+            _ => None,
+        };
+        GenomeArchive {
+            file: file,
+            interval: interval,
+            count: config["genome_archive_count"].as_u64().unwrap() as usize,
+        }
+    }
+
+    pub fn is_due(&self, timestep: u64) -> bool {
+        self.file.is_some() && timestep % self.interval == 0
+    }
+
+    pub fn write(&mut self, timestep: u64, generation: u64, species: i32, genome_id: u64, brain: &BrainNetwork) -> Result<(), Box<dyn Error>> {
+        if let Some(file) = self.file.as_mut() {
+            let entry = json!({
+                "timestep": timestep,
+                "generation": generation,
+                "species": SPECIES_NAMES[species as usize],
+                "genome_id": genome_id,
+                "brain": brain.to_json(),
+            });
+            writeln!(file, "{}", entry)?;
+        }
+        Ok(())
+    }
+}
+
+// The genomes archived at one timestep, indexed by the species id
+pub struct Snapshot {
+    pub timestep: u64,
+    pub genomes: Vec<Vec<Value>>,
+}
+
+// Reads an archive into snapshots ordered by timestep
+pub fn read_snapshots(filename: &str) -> Result<Vec<Snapshot>, Box<dyn Error>> {
+    let reader = BufReader::new(File::open(filename)?);
+    let mut snapshots: Vec<Snapshot> = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry: Value = serde_json::from_str(&line)?;
+        let timestep = entry["timestep"].as_u64().unwrap();
+        if snapshots.last().map(|snapshot| snapshot.timestep) != Some(timestep) {
+            snapshots.push(Snapshot {
+                timestep: timestep,
+                genomes: vec![Vec::new(); SPECIES_NAMES.len()],
+            });
+        }
+        let species = species_id(entry["species"].as_str().unwrap());
+        snapshots.last_mut().unwrap().genomes[species as usize].push(entry["brain"].clone());
+    }
+    Ok(snapshots)
+}
@@ -1,11 +1,24 @@
-use nalgebra::base::{MatrixMN, Matrix};
+use nalgebra::base::{DMatrix, DVector};
+use serde_json::{json, Value};
 
+use crate::crossover::CrossoverOperator;
 use crate::entity::Entity;
+use crate::mutation::{Mutation, MutationOperator};
 use crate::random_helper::RandomHelper;
+use crate::sharing::Sharing;
+use crate::species::{CommunicationConfig, SensorConfig};
 
 
-pub type InputProcessLayer1 = MatrixMN<f32, nalgebra::U7, nalgebra::U5>;
-pub type InputProcessLayer2 = MatrixMN<f32, nalgebra::U3, nalgebra::U7>;
+pub const HIDDEN_NEURONS: usize = 7;
+// Turn left, go straight, turn right
+pub const ACTIONS: usize = 3;
+// Energy, hunger, heading (sine and cosine) and pack mates
+pub const STATE_INPUTS: usize = 5;
+// Own reputation and mean reputation of the pack mates
+pub const REPUTATION_INPUTS: usize = 2;
+
+pub type InputProcessLayer1 = DMatrix<f32>;
+pub type InputProcessLayer2 = DMatrix<f32>;
 
 #[derive(Clone)]
 pub struct InputProcessorNetwork {
@@ -15,12 +28,12 @@ pub struct InputProcessorNetwork {
 }
 
 impl InputProcessorNetwork {
-    pub fn new(seed: u64) -> Self {
+    pub fn new(inputs: usize, outputs: usize, seed: u64) -> Self {
         // screen size is not important, because the RandomHelper is used to create new random neuron values 
         let mut rand = RandomHelper::new(500.0, 500.0, seed);
-        let mut layer1: InputProcessLayer1 = MatrixMN::zeros_generic(nalgebra::U7, nalgebra::U5);
+        let mut layer1: InputProcessLayer1 = DMatrix::zeros(HIDDEN_NEURONS, inputs);
         layer1 = layer1.map(|_| rand.random_between(-1.0, 1.0));
-        let mut layer2: InputProcessLayer2 = MatrixMN::zeros_generic(nalgebra::U3, nalgebra::U7);
+        let mut layer2: InputProcessLayer2 = DMatrix::zeros(outputs, HIDDEN_NEURONS);
         layer2 = layer2.map(|_| rand.random_between(-1.0, 1.0));
         InputProcessorNetwork {
             rand: rand,
@@ -29,22 +42,119 @@ impl InputProcessorNetwork {
         }
     }
 
-    pub fn mutate(& mut self) {
+    pub fn mutate(& mut self, mutation: &Mutation, sigma: f32) {
+        for weight in self.layer1.iter_mut() {
+            if self.rand.random_between(0.0, 1.0) < mutation.weight_rate {
+                *weight = mutation.perturb(*weight, sigma, &mut self.rand);
+            }
+        }
+        for weight in self.layer2.iter_mut() {
+            if self.rand.random_between(0.0, 1.0) < mutation.weight_rate {
+                *weight = mutation.perturb(*weight, sigma, &mut self.rand);
+            }
+        }
+    }
+
+    pub fn recombine(parents: &[&InputProcessorNetwork], operator: CrossoverOperator, rand: &mut RandomHelper) -> Self {
+        let mut child = parents[0].clone();
+        match operator {
+            CrossoverOperator::Uniform => {
+                for (index, weight) in child.layer1.iter_mut().enumerate() {
+                    *weight = parents[rand.random_between(0.0, parents.len() as f32) as usize % parents.len()].layer1[index];
+                }
+                for (index, weight) in child.layer2.iter_mut().enumerate() {
+                    *weight = parents[rand.random_between(0.0, parents.len() as f32) as usize % parents.len()].layer2[index];
+                }
+            }
+            CrossoverOperator::Neuron => {
+                for row in 0..child.layer1.nrows() {
+                    let parent = parents[rand.random_between(0.0, parents.len() as f32) as usize % parents.len()];
+                    child.layer1.set_row(row, &parent.layer1.row(row));
+                }
+                for row in 0..child.layer2.nrows() {
+                    let parent = parents[rand.random_between(0.0, parents.len() as f32) as usize % parents.len()];
+                    child.layer2.set_row(row, &parent.layer2.row(row));
+                }
+            }
+            CrossoverOperator::Arithmetic => {
+                let mut ratios: Vec<f32> = parents.iter().map(|_| rand.random_between(0.0, 1.0)).collect();
+                let sum: f32 = ratios.iter().sum();
+                for ratio in ratios.iter_mut() {
+                    *ratio /= sum;
+                }
+                child.layer1 = child.layer1.map(|_| 0.0);
+                child.layer2 = child.layer2.map(|_| 0.0);
+                for (parent, ratio) in parents.iter().zip(ratios.iter()) {
+                    child.layer1 += &parent.layer1 * *ratio;
+                    child.layer2 += &parent.layer2 * *ratio;
+                }
+            }
+            _ => (),
+        }
+        child
+    }
+
+    pub fn mutate_single(& mut self, mutation: &Mutation) {
         let layer = self.rand.random_between(0.0, 2.0) as i32;
         match layer {
             0 => {
+                let (rows, cols) = self.layer1.shape();
                 self.layer1[(
-                    self.rand.random_between(0.0, 7.0) as usize, self.rand.random_between(0.0, 5.0) as usize
-                )] = self.rand.random_between(- 1.0, 1.0);
+                    self.rand.random_between(0.0, rows as f32) as usize, self.rand.random_between(0.0, cols as f32) as usize
+                )] = mutation.clamp(self.rand.random_between(- 1.0, 1.0));
             }
             1 => {
+                let (rows, cols) = self.layer2.shape();
                 self.layer2[(
-                    self.rand.random_between(0.0, 3.0) as usize, self.rand.random_between(0.0, 7.0) as usize
-                )] = self.rand.random_between(- 1.0, 1.0);
+                    self.rand.random_between(0.0, rows as f32) as usize, self.rand.random_between(0.0, cols as f32) as usize
+                )] = mutation.clamp(self.rand.random_between(- 1.0, 1.0));
             }
             _ => (),
         }
     }
+
+    pub fn weights(&self) -> Vec<f32> {
+        self.layer1.iter().chain(self.layer2.iter()).cloned().collect()
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "layer1": InputProcessorNetwork::layer_to_json(&self.layer1),
+            "layer2": InputProcessorNetwork::layer_to_json(&self.layer2),
+        })
+    }
+
+    // The network has to have the same shape as the stored one
+    pub fn load_json(&mut self, value: &Value) {
+        InputProcessorNetwork::load_layer(&mut self.layer1, &value["layer1"]);
+        InputProcessorNetwork::load_layer(&mut self.layer2, &value["layer2"]);
+    }
+
+    // The weights are stored column by column, the order nalgebra keeps them in
+    fn layer_to_json(layer: &DMatrix<f32>) -> Value {
+        json!({
+            "rows": layer.nrows(),
+            "cols": layer.ncols(),
+            "weights": layer.iter().cloned().collect::<Vec<f32>>(),
+        })
+    }
+
+    fn load_layer(layer: &mut DMatrix<f32>, value: &Value) {
+        let rows = value["rows"].as_u64().unwrap() as usize;
+        let cols = value["cols"].as_u64().unwrap() as usize;
+        assert_eq!((rows, cols), layer.shape(), "The stored network has a different shape");
+        let weights: Vec<f32> = value["weights"].as_array().unwrap().iter().map(|weight| weight.as_f64().unwrap() as f32).collect();
+        *layer = DMatrix::from_column_slice(rows, cols, &weights);
+    }
+
+    pub fn process(&self, inputs: Vec<f32>) -> DVector<f32> {
+        let input = DVector::from_vec(inputs);
+        let mut output1 = &self.layer1 * input;
+        output1 = output1.map(Brain::sigmoid);
+        let mut output2 = &self.layer2 * output1;
+        output2 = output2.map(Brain::sigmoid);
+        output2
+    }
 }
 
 #[derive(Clone)]
@@ -53,56 +163,172 @@ pub struct BrainNetwork {
     pub wall_network: InputProcessorNetwork,
     pub food_network: InputProcessorNetwork,
     pub carnivore_network: InputProcessorNetwork,
+    // Processes agents of the own species, only used with species aware sensors
+    pub ally_network: InputProcessorNetwork,
+    // Processes the agent's internal state, only used when it is enabled in the sensors
+    pub state_network: InputProcessorNetwork,
+    // Produces the emitted signal from the food and carnivore channels and the internal state
+    pub signal_network: InputProcessorNetwork,
+    // Processes the received signal
+    pub hearing_network: InputProcessorNetwork,
+    // Processes the reputations, only used when they are enabled in the sensors
+    pub reputation_network: InputProcessorNetwork,
+    // Mutation step size, only evolves when self-adaptive mutation is enabled
+    pub mutation_sigma: f32,
+    // Fraction of a prey shared with the pack, only evolves when the species' sharing is evolvable
+    pub sharing: f32,
+    // Whether the sub-network in the same place of networks() is in use. The others are neither mutated nor compared
+    pub enabled: Vec<bool>,
 }
 
 impl BrainNetwork {
-    pub fn new(seed: u64) -> Self {
+    pub fn new(inputs: usize, signal_dimensions: usize, seed: u64, mutation_sigma: f32, sharing: f32) -> Self {
         // screen size is not important here, because the RandomHelper is used to create new random neuron values
         let rand = RandomHelper::new(500.0, 500.0, seed);
         
         BrainNetwork {
             rand: rand,
-            wall_network: InputProcessorNetwork::new(seed + 1),
-            food_network: InputProcessorNetwork::new(seed + 2),
-            carnivore_network: InputProcessorNetwork::new(seed + 3),
+            wall_network: InputProcessorNetwork::new(inputs, ACTIONS, seed + 1),
+            food_network: InputProcessorNetwork::new(inputs, ACTIONS, seed + 2),
+            carnivore_network: InputProcessorNetwork::new(inputs, ACTIONS, seed + 3),
+            ally_network: InputProcessorNetwork::new(inputs, ACTIONS, seed + 4),
+            state_network: InputProcessorNetwork::new(STATE_INPUTS, ACTIONS, seed + 5),
+            signal_network: InputProcessorNetwork::new(inputs * 2 + STATE_INPUTS, signal_dimensions, seed + 6),
+            hearing_network: InputProcessorNetwork::new(signal_dimensions, ACTIONS, seed + 7),
+            reputation_network: InputProcessorNetwork::new(REPUTATION_INPUTS, ACTIONS, seed + 8),
+            mutation_sigma: mutation_sigma,
+            sharing: sharing,
+            enabled: vec![true; 8],
         }
     }
 
-    pub fn mutate(& mut self) {
-        let network = self.rand.random_between(0.0, 3.0) as i32;
-        match network {
-            0 => {
-                self.wall_network.mutate();
-            }
-            1 => {
-                self.food_network.mutate();
-            }
-            2 => {
-                self.carnivore_network.mutate();
+    // Sub-networks in the order used by the crossover operators
+    pub fn networks(&self) -> Vec<&InputProcessorNetwork> {
+        vec![
+            &self.food_network, &self.wall_network, &self.carnivore_network, &self.ally_network, &self.state_network,
+            &self.signal_network, &self.hearing_network, &self.reputation_network,
+        ]
+    }
+
+    // Every weight of the sub-networks in use in a single vector, used to compare genomes
+    pub fn weights(&self) -> Vec<f32> {
+        self.networks().iter()
+            .zip(self.enabled.iter())
+            .filter(|(_, &enabled)| enabled)
+            .flat_map(|(network, _)| network.weights())
+            .collect()
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "mutation_sigma": self.mutation_sigma,
+            "sharing": self.sharing,
+            "networks": self.networks().iter().map(|network| network.to_json()).collect::<Vec<Value>>(),
+        })
+    }
+
+    // Replaces the weights with stored ones, the sub-networks have to have the same shapes.
+    // Brains stored before the reputation network existed keep their random one
+    pub fn load_json(&mut self, value: &Value) {
+        self.mutation_sigma = value["mutation_sigma"].as_f64().unwrap() as f32;
+        if let Some(sharing) = value["sharing"].as_f64() {
+            self.sharing = sharing as f32;
+        }
+        let networks = value["networks"].as_array().unwrap();
+        assert!(networks.len() <= self.networks().len(), "The stored brain has more sub-networks");
+        for (network, stored) in self.networks_mut().into_iter().zip(networks.iter()) {
+            network.load_json(stored);
+        }
+    }
+
+    fn networks_mut(&mut self) -> Vec<&mut InputProcessorNetwork> {
+        vec![
+            &mut self.food_network, &mut self.wall_network, &mut self.carnivore_network, &mut self.ally_network, &mut self.state_network,
+            &mut self.signal_network, &mut self.hearing_network, &mut self.reputation_network,
+        ]
+    }
+
+    // Sub-network k is taken whole from parent k (modulo the number of parents) with the
+    // subnetwork operator, otherwise every sub-network is recombined from all parents
+    pub fn recombine(parents: &[BrainNetwork], operator: CrossoverOperator, rand: &mut RandomHelper) -> Self {
+        let mut child = parents[0].clone();
+        let network_count = child.networks().len();
+        for k in 0..network_count {
+            let network = if operator == CrossoverOperator::Subnetwork {
+                parents[k % parents.len()].networks()[k].clone()
+            } else {
+                let networks: Vec<&InputProcessorNetwork> = parents.iter().map(|p| p.networks()[k]).collect();
+                InputProcessorNetwork::recombine(&networks, operator, rand)
+            };
+            *child.networks_mut()[k] = network;
+        }
+        if operator == CrossoverOperator::Arithmetic {
+            child.mutation_sigma = parents.iter().map(|p| p.mutation_sigma).sum::<f32>() / parents.len() as f32;
+            child.sharing = parents.iter().map(|p| p.sharing).sum::<f32>() / parents.len() as f32;
+        }
+        child
+    }
+
+    pub fn mutate_sharing(&mut self, sharing: &Sharing) {
+        self.sharing = sharing.clamp_gene(self.sharing + self.rand.random_gaussian(0.0, sharing.gene_sigma));
+    }
+
+    pub fn mutate(& mut self, mutation: &Mutation) {
+        let enabled = self.enabled.clone();
+        if mutation.operator == MutationOperator::Single {
+            let count = enabled.iter().filter(|&&enabled| enabled).count();
+            let network = self.rand.random_between(0.0, count as f32) as usize;
+            let mut networks = self.networks_mut().into_iter().zip(enabled.iter()).filter(|(_, &enabled)| enabled);
+            if let Some((network, _)) = networks.nth(network) {
+                network.mutate_single(mutation);
             }
-            _ => (),
+            return;
+        }
+        let mut sigma = mutation.sigma;
+        if mutation.self_adaptive {
+            self.mutation_sigma = mutation.adapt_sigma(self.mutation_sigma, &mut self.rand);
+            sigma = self.mutation_sigma;
+        }
+        for (network, _) in self.networks_mut().into_iter().zip(enabled.iter()).filter(|(_, &enabled)| enabled) {
+            network.mutate(mutation, sigma);
         }
     }
 }
 
 pub struct Brain {
     rand: RandomHelper,
-    view_range: f32,
-    mutation_rate: f32,
+    sensors: SensorConfig,
+    communication: CommunicationConfig,
+    mutation: Mutation,
+    sharing: Sharing,
     brain_network: BrainNetwork,
 }
 
 impl Brain {
-    pub fn new(view_range: f32, mutation_rate: f32, seed: u64) -> Self {
+    pub fn new(sensors: SensorConfig, communication: CommunicationConfig, mutation: Mutation, sharing: Sharing, seed: u64) -> Self {
         let rand = RandomHelper::new(500.0, 500.0, seed);
+        let mut brain_network = BrainNetwork::new(
+            sensors.total_rays(), communication.dimensions, seed + 777, mutation.sigma, sharing.initial_gene
+        );
+        brain_network.enabled = Brain::enabled_networks(&sensors, &communication);
         Brain {
             rand: rand,
-            view_range: view_range,
-            mutation_rate: mutation_rate,
-            brain_network: BrainNetwork::new(seed + 777),
+            sensors: sensors,
+            communication: communication,
+            mutation: mutation,
+            sharing: sharing,
+            brain_network: brain_network,
         }
     }
 
+    // Which sub-networks the sensors and the communication use, in the order of BrainNetwork::networks
+    fn enabled_networks(sensors: &SensorConfig, communication: &CommunicationConfig) -> Vec<bool> {
+        vec![
+            true, true, true, sensors.species_aware, sensors.internal_state,
+            communication.emit, communication.hears.len() > 0, sensors.reputation,
+        ]
+    }
+
     pub fn get_networks(& self) -> BrainNetwork {
         self.brain_network.clone()
     }
@@ -111,110 +337,110 @@ impl Brain {
         self.brain_network = brain_network;
     }
 
+    pub fn weights(&self) -> Vec<f32> {
+        self.brain_network.weights()
+    }
+
+    pub fn sharing_gene(&self) -> f32 {
+        self.brain_network.sharing
+    }
+
     pub fn mutate(& mut self) {
-        if (self.mutation_rate as f32) < self.rand.random_between(0.0, 1.0) {
-            self.brain_network.mutate();
+        if self.rand.random_between(0.0, 1.0) < self.mutation.rate {
+            self.brain_network.mutate(&self.mutation);
+            if self.sharing.evolvable {
+                self.brain_network.mutate_sharing(&self.sharing);
+            }
         }
     }
 
-    pub fn get_new_direction(&self, closest_objects: Vec<Option<(Entity, f32)>>, brain_entity: Entity, facing: i8) -> i8 {
-        let step = 5;
+    // Returns the new facing and the emitted signal, which is empty when the species does not emit
+    pub fn get_new_direction(&self, closest_objects: Vec<Option<(Entity, i32, f32)>>, brain_entity: Entity, brain_species: i32, facing: i8,
+        internal_state: Vec<f32>, heard_signal: Vec<f32>, reputation: Vec<f32>
+    ) -> (i8, Vec<f32>) {
+        let ranges = self.sensors.ray_ranges();
         let rays = closest_objects.len();
-        let input_size = step * rays;
-        let mut input_vec = vec![0.0; input_size];
-        if brain_entity == Entity::HERBIVORE {
-            for i in (0..input_size).step_by(step) {
-                if let Some((entity, toi)) = &closest_objects[i / step] {
-                    match entity {
-                        Entity::FOOD => input_vec[i] = 1.0,
-                        Entity::CARNIVORE => {
-                            input_vec[i + 1] = 1.0;
-                            },
-                            Entity::WALL => {
-                                input_vec[i + 2] = 1.0;
-                                },
-                        _ => (),
-                    }
-                    input_vec[i + 3] = 1.0 - *toi / self.view_range;
-                }
-                else {
-                    input_vec[i + 4] = 1.0;
+        // Every sub-network gets one input per ray: the proximity of the detected object of its kind
+        let mut food_inputs = vec![0.0; rays];
+        let mut carnivore_inputs = vec![0.0; rays];
+        let mut wall_inputs = vec![0.0; rays];
+        let mut ally_inputs = vec![0.0; rays];
+        for (i, closest_object) in closest_objects.iter().enumerate() {
+            if let Some((entity, species, toi)) = closest_object {
+                let proximity = 1.0 - *toi / ranges[i];
+                match (brain_entity, entity) {
+                    _ if self.sensors.species_aware && *species == brain_species => ally_inputs[i] = proximity,
+                    (Entity::HERBIVORE, Entity::FOOD) => food_inputs[i] = proximity,
+                    (Entity::CARNIVORE, Entity::HERBIVORE) => food_inputs[i] = proximity,
+                    (_, Entity::CARNIVORE) => carnivore_inputs[i] = proximity,
+                    (_, Entity::WALL) => wall_inputs[i] = proximity,
+                    _ => (),
                 }
             }
         }
-        else {
-            for i in (0..input_size).step_by(step) {
-                if let Some((entity, toi)) = &closest_objects[i / step] {
-                    match entity {
-                        Entity::FOOD => (),
-                        Entity::HERBIVORE => {
-                            input_vec[i] = 1.0;
-                        },
-                        Entity::CARNIVORE => input_vec[i + 1] = 1.0,
-                        Entity::WALL => {
-                            input_vec[i + 2] = 1.0;
-                        },
-                        _ => (),
-                    }
-                    input_vec[i + 3] = 1.0 - *toi / self.view_range;
-                }
-                else {
-                    input_vec[i + 4] = 1.0;
-                }
-            }
-        }
-        
-        let mut inputs = Vec::new();
-        for j in 0..3 {
-            for i in (0..input_size).step_by(step) {
-                inputs.push(input_vec[i + j] * input_vec[i + 3]);
+
+        let mut signal = Vec::new();
+        if self.communication.emit {
+            let mut signal_inputs = food_inputs.clone();
+            signal_inputs.extend_from_slice(&carnivore_inputs);
+            if internal_state.len() == STATE_INPUTS {
+                signal_inputs.extend_from_slice(&internal_state);
+            } else {
+                signal_inputs.extend_from_slice(&[0.0; STATE_INPUTS]);
             }
+            signal = self.brain_network.signal_network.process(signal_inputs).iter().cloned().collect();
         }
 
+        let mut outputs = vec![
+            self.brain_network.food_network.process(food_inputs),
+            self.brain_network.carnivore_network.process(carnivore_inputs),
+            self.brain_network.wall_network.process(wall_inputs),
+        ];
+        if self.sensors.species_aware {
+            outputs.push(self.brain_network.ally_network.process(ally_inputs));
+        }
+        if self.sensors.internal_state {
+            outputs.push(self.brain_network.state_network.process(internal_state));
+        }
+        if self.communication.hears.len() > 0 {
+            outputs.push(self.brain_network.hearing_network.process(heard_signal));
+        }
+        if self.sensors.reputation {
+            outputs.push(self.brain_network.reputation_network.process(reputation));
+        }
+        let mut action_values = DVector::zeros(ACTIONS);
+        for output in outputs {
+            action_values += output;
+        }
         let mut max_i = 1;
-        let mut relevant_inputs = Vec::new();
-        for i in (0..24).step_by(8) {
-            relevant_inputs.push(inputs[(facing as usize + 6) % 8 + i]);
-            relevant_inputs.push(inputs[(facing as usize + 7) % 8 + i]);
-            relevant_inputs.push(inputs[(facing as usize) % 8 + i]);
-            relevant_inputs.push(inputs[(facing as usize + 1) % 8 + i]);
-            relevant_inputs.push(inputs[(facing as usize + 2) % 8 + i]);
-        }
-
-        let input1 = Matrix::<f32, nalgebra::U5, nalgebra::U1, _>::from_vec((&relevant_inputs[..5]).to_vec());
-        let mut first_output1 = self.brain_network.food_network.layer1 * input1;
-        first_output1 = first_output1.map(Brain::sigmoid);
-        let mut first_output2 = self.brain_network.food_network.layer2 * first_output1;
-        first_output2 = first_output2.map(Brain::sigmoid);
-        let input2 = Matrix::<f32, nalgebra::U5, nalgebra::U1, _>::from_vec((&relevant_inputs[5..10]).to_vec());
-        let mut second_output1 = self.brain_network.carnivore_network.layer1 * input2;
-        second_output1 = second_output1.map(Brain::sigmoid);
-        let mut second_output2 = self.brain_network.carnivore_network.layer2 * second_output1;
-        second_output2 = second_output2.map(Brain::sigmoid);
-        let input3 = Matrix::<f32, nalgebra::U5, nalgebra::U1, _>::from_vec((&relevant_inputs[10..]).to_vec());
-        let mut third_output1 = self.brain_network.wall_network.layer1 * input3;
-        third_output1 = third_output1.map(Brain::sigmoid);
-        let mut third_output2 = self.brain_network.wall_network.layer2 * third_output1;
-        third_output2 = third_output2.map(Brain::sigmoid);
-        for i in 0..3 {
-            if first_output2[i] + second_output2[i] + third_output2[i] > first_output2[max_i] + second_output2[max_i] + third_output2[max_i] {
+        for i in 0..ACTIONS {
+            if action_values[i] > action_values[max_i] {
                 max_i = i;
             }
         }
 
-        match max_i {
+        let new_facing = match max_i {
             0 => (facing + 7) % 8,
             1 => facing,
             2 => (facing + 1) % 8,
             _ => -1,
-        }
+        };
+        (new_facing, signal)
     }
 
-    pub fn max_sensor_distance(&self) -> f32 {
-        self.view_range
+    pub fn sensors(&self) -> &SensorConfig {
+        &self.sensors
+    }
+
+    pub fn communication(&self) -> &CommunicationConfig {
+        &self.communication
+    }
+
+    pub fn mutation(&self) -> &Mutation {
+        &self.mutation
     }
 
     pub fn sigmoid(num: f32) -> f32 {
         num / (num.abs() + 0.5)
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,97 @@
+use serde_json::{json, Value};
+
+// How hard a prey is to catch, the pack is the hunter and the other carnivores within its share range
+#[derive(Clone)]
+pub struct Capture {
+    // Smaller packs cannot catch the prey
+    pub min_hunters: usize,
+    // Capture probability of a lone hunter and its increase with every further pack member
+    pub probability: f32,
+    pub probability_per_hunter: f32,
+    // Extra share of the prey's nutrition for every further pack member
+    pub yield_per_hunter: f32,
+}
+
+impl Capture {
+    // Without a capture block the prey is always caught by a lone hunter
+    pub fn from_config(config: &Value) -> Self {
+        if config.is_null() {
+            return Capture::from_config(&json!({
+                "min_hunters": 1,
+                "probability": 1.0,
+                "probability_per_hunter": 0.0,
+                "yield_per_hunter": 0.0
+            }));
+        }
+        Capture {
+            min_hunters: (config["min_hunters"].as_u64().unwrap() as usize).max(1),
+            probability: config["probability"].as_f64().unwrap() as f32,
+            probability_per_hunter: config["probability_per_hunter"].as_f64().unwrap() as f32,
+            yield_per_hunter: config["yield_per_hunter"].as_f64().unwrap() as f32,
+        }
+    }
+
+    // No random number is drawn for prey that is always caught
+    pub fn is_random(&self) -> bool {
+        self.probability < 1.0 || self.probability_per_hunter < 0.0
+    }
+
+    pub fn probability(&self, pack: usize) -> f32 {
+        (self.probability + self.probability_per_hunter * (pack - 1) as f32).max(0.0).min(1.0)
+    }
+
+    // The roll is uniform in [0, 1)
+    pub fn succeeds(&self, pack: usize, roll: f32) -> bool {
+        pack >= self.min_hunters && roll < self.probability(pack)
+    }
+
+    pub fn yield_factor(&self, pack: usize) -> f32 {
+        (1.0 + self.yield_per_hunter * (pack - 1) as f32).max(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capture(min_hunters: u64, probability: f64, probability_per_hunter: f64, yield_per_hunter: f64) -> Capture {
+        Capture::from_config(&json!({
+            "min_hunters": min_hunters,
+            "probability": probability,
+            "probability_per_hunter": probability_per_hunter,
+            "yield_per_hunter": yield_per_hunter
+        }))
+    }
+
+    #[test]
+    fn missing_block_always_catches() {
+        let capture = Capture::from_config(&Value::Null);
+        assert!(!capture.is_random());
+        assert!(capture.succeeds(1, 0.999));
+        assert_eq!(capture.yield_factor(3), 1.0);
+    }
+
+    #[test]
+    fn succeeds_needs_the_pack_and_the_roll() {
+        let capture = capture(2, 0.25, 0.25, 0.0);
+        assert!(capture.is_random());
+        assert!(!capture.succeeds(1, 0.0));
+        assert!(capture.succeeds(2, 0.49));
+        assert!(!capture.succeeds(2, 0.5));
+        // The probability is capped at 1
+        assert!(capture.succeeds(10, 0.999));
+    }
+
+    #[test]
+    fn min_hunters_is_at_least_one() {
+        assert_eq!(capture(0, 1.0, 0.0, 0.0).min_hunters, 1);
+    }
+
+    #[test]
+    fn yield_factor_grows_with_the_pack() {
+        let capture = capture(1, 1.0, 0.0, 0.5);
+        assert_eq!(capture.yield_factor(1), 1.0);
+        assert_eq!(capture.yield_factor(3), 2.0);
+        assert_eq!(Capture { yield_per_hunter: -1.0, ..capture }.yield_factor(3), 0.0);
+    }
+}
@@ -0,0 +1,70 @@
+use serde_json::Value;
+use std::error::Error;
+
+use crate::archive::read_snapshots;
+use crate::config::read_config_from_file;
+use crate::gamestate::GameState;
+use crate::record::Writer;
+use crate::species::{HERBIVORE_SPECIES, CARNIVORE_SPECIES_1, CARNIVORE_SPECIES_2, SPECIES_NAMES};
+
+// Current Individual vs Ancestral Opponents: the herbivores of every archived timestep play against the
+// carnivores of every archived timestep in a headless world, one matrix of average scores per species
+pub fn run(archive_filename: &str) -> Result<(), Box<dyn Error>> {
+    let (mut config, wall_config) = read_config_from_file()?;
+    // The archived genomes are evaluated as they are, offspring are unmutated copies of a single parent
+    config["evolution_mode"] = Value::from("steady_state");
+    config["mutation_rate"] = Value::from(0.0);
+    config["crossover_operator"] = Value::from("none");
+    config["islands"] = Value::Array(Vec::new());
+    let steps = config["ciao_evaluation_steps"].as_u64().unwrap() as u64;
+
+    let snapshots = read_snapshots(archive_filename)?;
+    let herbivore_snapshots: Vec<_> = snapshots.iter().filter(|snapshot| !snapshot.genomes[HERBIVORE_SPECIES as usize].is_empty()).collect();
+    let carnivore_snapshots: Vec<_> = snapshots.iter()
+        .filter(|snapshot| !snapshot.genomes[CARNIVORE_SPECIES_1 as usize].is_empty() || !snapshot.genomes[CARNIVORE_SPECIES_2 as usize].is_empty())
+        .collect();
+
+    // A species without archived genomes in some of its snapshots would play with random brains, it is
+    // left out of the evaluation
+    let mut species = vec![HERBIVORE_SPECIES];
+    for &carnivore_species in [CARNIVORE_SPECIES_1, CARNIVORE_SPECIES_2].iter() {
+        if carnivore_snapshots.iter().all(|snapshot| !snapshot.genomes[carnivore_species as usize].is_empty()) {
+            species.push(carnivore_species);
+        } else {
+            println!("Skipping {}: it has no archived genomes in every carnivore timestep", SPECIES_NAMES[carnivore_species as usize]);
+        }
+    }
+    let mut writers: Vec<Writer> = species.iter().map(|&species| Writer::new(&format!("ciao_{}.csv", SPECIES_NAMES[species as usize]))).collect();
+    // Rows are the herbivore timesteps, columns the carnivore timesteps
+    let mut header = vec![String::from("herbivore_timestep")];
+    header.extend(carnivore_snapshots.iter().map(|snapshot| snapshot.timestep.to_string()));
+    for writer in writers.iter_mut() {
+        writer.write_row(&header)?;
+    }
+
+    for herbivore_snapshot in herbivore_snapshots.iter() {
+        let mut rows: Vec<Vec<String>> = vec![vec![herbivore_snapshot.timestep.to_string()]; species.len()];
+        for carnivore_snapshot in carnivore_snapshots.iter() {
+            println!("Herbivores of timestep {} against carnivores of timestep {}", herbivore_snapshot.timestep, carnivore_snapshot.timestep);
+            let mut state = GameState::new((config.clone(), wall_config.clone()), None);
+            state.load_genomes(HERBIVORE_SPECIES, &herbivore_snapshot.genomes[HERBIVORE_SPECIES as usize]);
+            state.load_genomes(CARNIVORE_SPECIES_1, &carnivore_snapshot.genomes[CARNIVORE_SPECIES_1 as usize]);
+            state.load_genomes(CARNIVORE_SPECIES_2, &carnivore_snapshot.genomes[CARNIVORE_SPECIES_2 as usize]);
+            let mut totals = vec![0.0; species.len()];
+            for _ in 0..steps {
+                state.step();
+                for (total, &species) in totals.iter_mut().zip(species.iter()) {
+                    *total += state.average_score(species);
+                }
+            }
+            for (row, total) in rows.iter_mut().zip(totals.iter()) {
+                let average = if steps == 0 { 0.0 } else { total / steps as f32 };
+                row.push(average.to_string());
+            }
+        }
+        for (writer, row) in writers.iter_mut().zip(rows.iter()) {
+            writer.write_row(row)?;
+        }
+    }
+    Ok(())
+}
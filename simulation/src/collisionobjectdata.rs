@@ -2,28 +2,57 @@ use ncollide2d::pipeline::CollisionObjectSlabHandle;
 use std::cell::Cell;
 
 use crate::entity::Entity;
+use crate::species::MAX_SIGNAL_DIMENSIONS;
 
 #[derive(Clone)]
 pub struct CollisionObjectData {
     pub entity_type: Entity,
     pub id: i32,
+    pub species: i32,
     pub env_handle: Option<CollisionObjectSlabHandle>,
     pub fitness: Cell<i32>,
     pub eaten: Cell<bool>,
+    // What the agent ran into when it was eaten
+    pub killed_by: Cell<Option<Entity>>,
     pub energy: Cell<i32>,
     pub score: Cell<i32>,
+    // Energy given to pack mates and hunts taken part in since the agent's last update
+    pub shared: Cell<i32>,
+    pub hunts: Cell<i32>,
+    // Decisions in a row in which the agent saw its prey
+    pub chase: Cell<i32>,
+    // The agent's sharing gene
+    pub sharing: Cell<f32>,
+    // Energy given to and received from other carnivores, prey caught and shares withheld from the agent during its life
+    pub given: Cell<i32>,
+    pub received: Cell<i32>,
+    pub kills: Cell<i32>,
+    pub withheld: Cell<i32>,
+    // Last signal emitted by the agent
+    pub signal: Cell<[f32; MAX_SIGNAL_DIMENSIONS]>,
 }
 
 impl CollisionObjectData {
-    pub fn new(entity_type: Entity, id: i32, env_handle: Option<CollisionObjectSlabHandle>) -> CollisionObjectData {
+    pub fn new(entity_type: Entity, id: i32, species: i32, env_handle: Option<CollisionObjectSlabHandle>) -> CollisionObjectData {
         CollisionObjectData {
             entity_type: entity_type,
             id: id,
+            species: species,
             env_handle: env_handle,
             fitness: Cell::new(0),
             eaten: Cell::new(false),
+            killed_by: Cell::new(None),
             energy: Cell::new(400),
             score: Cell::new(0),
+            shared: Cell::new(0),
+            hunts: Cell::new(0),
+            chase: Cell::new(0),
+            sharing: Cell::new(0.0),
+            given: Cell::new(0),
+            received: Cell::new(0),
+            kills: Cell::new(0),
+            withheld: Cell::new(0),
+            signal: Cell::new([0.0; MAX_SIGNAL_DIMENSIONS]),
         }
     }
 }
\ No newline at end of file
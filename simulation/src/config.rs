@@ -10,12 +10,32 @@ pub fn read_config_from_file() -> Result<(Value, Value), Box<dyn Error>> {
 
     let config: Value = serde_json::from_reader(reader)?;
     
-    let wall_file_name = config["map"].as_str().unwrap();
+    let wall_config = read_map(config["map"].as_str().unwrap())?;
+
+    // Return the `Config`.
+    Ok((config, wall_config))
+}
+
+pub fn read_map(wall_file_name: &str) -> Result<Value, Box<dyn Error>> {
     let wall_file = File::open("walls/".to_owned() + wall_file_name + ".json")?;
     let wall_reader = BufReader::new(wall_file);
 
     let wall_config: Value = serde_json::from_reader(wall_reader)?;
+    Ok(wall_config)
+}
 
-    // Return the `Config`.
-    Ok((config, wall_config))
-}
\ No newline at end of file
+// The parameters of every island: the top level keys of the island's entry replace the ones of the
+// base parameters. Islands without their own seed get the base seed plus their index.
+pub fn read_island_configs(config: &Value) -> Result<Vec<(Value, Value)>, Box<dyn Error>> {
+    let mut island_configs = Vec::new();
+    for (index, island) in config["islands"].as_array().unwrap().iter().enumerate() {
+        let mut island_config = config.clone();
+        island_config["seed"] = Value::from(config["seed"].as_u64().unwrap() + index as u64);
+        for (key, value) in island.as_object().unwrap() {
+            island_config[key] = value.clone();
+        }
+        let wall_config = read_map(island_config["map"].as_str().unwrap())?;
+        island_configs.push((island_config, wall_config));
+    }
+    Ok(island_configs)
+}
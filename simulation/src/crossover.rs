@@ -0,0 +1,59 @@
+use serde_json::Value;
+
+use crate::brain::BrainNetwork;
+use crate::random_helper::RandomHelper;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CrossoverOperator {
+    // Every sub-network is taken whole from one of the parents (the original scheme)
+    Subnetwork,
+    // Every weight is taken from a randomly chosen parent
+    Uniform,
+    // The incoming weights of every neuron are taken together from a randomly chosen parent
+    Neuron,
+    // Every weight is a random convex combination of the parents' weights
+    Arithmetic,
+    // The offspring is a copy of the first parent
+    None,
+}
+
+impl CrossoverOperator {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "subnetwork" => CrossoverOperator::Subnetwork,
+            "uniform" => CrossoverOperator::Uniform,
+            "neuron" => CrossoverOperator::Neuron,
+            "arithmetic" => CrossoverOperator::Arithmetic,
+            "none" => CrossoverOperator::None,
+            _ => panic!("Unknown crossover operator: {}", name),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Crossover {
+    pub operator: CrossoverOperator,
+    pub parents: usize,
+}
+
+impl Crossover {
+    pub fn from_config(config: &Value) -> Self {
+        let operator = CrossoverOperator::from_name(config["crossover_operator"].as_str().unwrap());
+        let mut parents = config["crossover_parents"].as_u64().unwrap() as usize;
+        if operator == CrossoverOperator::None || parents < 1 {
+            parents = 1;
+        }
+        Crossover {
+            operator: operator,
+            parents: parents,
+        }
+    }
+
+    // Creates the offspring genome, the first parent is the main parent
+    pub fn combine(&self, parents: &[BrainNetwork], rand: &mut RandomHelper) -> BrainNetwork {
+        match self.operator {
+            CrossoverOperator::None => parents[0].clone(),
+            _ => BrainNetwork::recombine(parents, self.operator, rand),
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use serde_json::Value;
+
+use crate::brain::ACTIONS;
+
+#[derive(Clone)]
+pub struct Diversity {
+    // Timesteps between two reports
+    pub interval: u64,
+    // Genomes closer than this to a cluster's first genome belong to that cluster
+    pub cluster_distance: f32,
+}
+
+impl Diversity {
+    pub fn from_config(config: &Value) -> Self {
+        Diversity {
+            interval: config["diversity_interval"].as_u64().unwrap() as u64,
+            cluster_distance: config["diversity_cluster_distance"].as_f64().unwrap() as f32,
+        }
+    }
+
+    pub fn is_due(&self, timestep: u64) -> bool {
+        self.interval > 0 && timestep % self.interval == 0
+    }
+
+    // Euclidean distance between two weight vectors of the same species
+    pub fn distance(first: &[f32], second: &[f32]) -> f32 {
+        first.iter().zip(second.iter()).map(|(a, b)| (a - b).powi(2)).sum::<f32>().sqrt()
+    }
+
+    pub fn mean_pairwise_distance(genomes: &[Vec<f32>]) -> f32 {
+        let mut sum = 0.0;
+        let mut pairs = 0;
+        for i in 0..genomes.len() {
+            for j in (i + 1)..genomes.len() {
+                sum += Diversity::distance(&genomes[i], &genomes[j]);
+                pairs += 1;
+            }
+        }
+        if pairs == 0 {
+            0.0
+        } else {
+            sum / pairs as f32
+        }
+    }
+
+    // Leader clustering: every genome joins the first cluster whose leader is close enough,
+    // otherwise it becomes the leader of a new cluster
+    pub fn clusters(&self, genomes: &[Vec<f32>]) -> usize {
+        let mut leaders: Vec<&Vec<f32>> = Vec::new();
+        for genome in genomes {
+            if !leaders.iter().any(|leader| Diversity::distance(leader, genome) < self.cluster_distance) {
+                leaders.push(genome);
+            }
+        }
+        leaders.len()
+    }
+
+    // Share of every action and the Shannon entropy of the distribution in bits
+    pub fn action_distribution(counts: &[u64; ACTIONS]) -> ([f32; ACTIONS], f32) {
+        let total: u64 = counts.iter().sum();
+        let mut shares = [0.0; ACTIONS];
+        let mut entropy = 0.0;
+        if total > 0 {
+            for (share, &count) in shares.iter_mut().zip(counts.iter()) {
+                *share = count as f32 / total as f32;
+                if *share > 0.0 {
+                    entropy -= *share * share.log2();
+                }
+            }
+        }
+        (shares, entropy)
+    }
+}
@@ -0,0 +1,20 @@
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum EvolutionMode {
+    // Dead agents respawn immediately with an offspring genome
+    SteadyState,
+    // Dead agents wait for the end of the episode, then the whole population is bred at once
+    Generational,
+    // Dead agents stay dead, agents with enough energy give birth to mutated copies of themselves
+    Ecological,
+}
+
+impl EvolutionMode {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "steady_state" => EvolutionMode::SteadyState,
+            "generational" => EvolutionMode::Generational,
+            "ecological" => EvolutionMode::Ecological,
+            _ => panic!("Unknown evolution mode: {}", name),
+        }
+    }
+}
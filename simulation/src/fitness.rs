@@ -0,0 +1,161 @@
+use serde_json::Value;
+
+use crate::diversity::Diversity;
+use crate::pareto::Objective;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FitnessMode {
+    // The score: one point per survived step plus the gained energy
+    Score,
+    // How different the agent's behavior is from the population and the archive
+    Novelty,
+    // The score plus the weighted novelty
+    Combined,
+    // NSGA-II ranking by the Pareto front and the crowding distance on the objectives
+    Pareto,
+}
+
+impl FitnessMode {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "score" => FitnessMode::Score,
+            "novelty" => FitnessMode::Novelty,
+            "combined" => FitnessMode::Combined,
+            "pareto" => FitnessMode::Pareto,
+            _ => panic!("Unknown fitness mode: {}", name),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Fitness {
+    pub mode: FitnessMode,
+    // Number of nearest neighbours the novelty is averaged over
+    pub novelty_k: usize,
+    // Score points one unit of novelty is worth in the combined mode
+    pub novelty_weight: f32,
+    // Behaviors at least this novel enter the archive when the agent dies
+    pub archive_threshold: f32,
+    pub archive_size: usize,
+    // The map is divided into grid x grid cells to measure the covered area
+    pub grid: usize,
+    // Objectives of the Pareto ranking and the Pareto front report
+    pub objectives: Vec<Objective>,
+}
+
+impl Fitness {
+    pub fn from_config(config: &Value) -> Self {
+        Fitness {
+            mode: FitnessMode::from_name(config["mode"].as_str().unwrap()),
+            novelty_k: (config["novelty_k"].as_u64().unwrap() as usize).max(1),
+            novelty_weight: config["novelty_weight"].as_f64().unwrap() as f32,
+            archive_threshold: config["archive_threshold"].as_f64().unwrap() as f32,
+            archive_size: config["archive_size"].as_u64().unwrap() as usize,
+            grid: (config["grid"].as_u64().unwrap() as usize).max(1),
+            objectives: config["objectives"].as_array().unwrap().iter().map(|name| Objective::from_name(name.as_str().unwrap())).collect(),
+        }
+    }
+
+    // Mean distance to the k nearest behaviors of the population (except the agent's own) and the archive
+    pub fn novelty(&self, descriptor: &[f32], population: &[Vec<f32>], own: Option<usize>, archive: &NoveltyArchive) -> f32 {
+        let mut distances: Vec<f32> = population.iter()
+            .enumerate()
+            .filter(|(i, _)| Some(*i) != own)
+            .map(|(_, other)| other)
+            .chain(archive.descriptors.iter())
+            .map(|other| Diversity::distance(descriptor, other))
+            .collect();
+        if distances.is_empty() {
+            return 0.0;
+        }
+        distances.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let k = self.novelty_k.min(distances.len());
+        distances[..k].iter().sum::<f32>() / k as f32
+    }
+
+    // Not used in the Pareto mode, where the fitness depends on the whole population
+    pub fn combine(&self, score: i32, novelty: f32) -> f32 {
+        match self.mode {
+            FitnessMode::Score | FitnessMode::Pareto => score as f32,
+            FitnessMode::Novelty => novelty,
+            FitnessMode::Combined => score as f32 + self.novelty_weight * novelty,
+        }
+    }
+}
+
+// Behaviors of dead agents that were novel when they died
+#[derive(Clone)]
+pub struct NoveltyArchive {
+    pub descriptors: Vec<Vec<f32>>,
+}
+
+impl NoveltyArchive {
+    pub fn new() -> Self {
+        NoveltyArchive {
+            descriptors: Vec::new(),
+        }
+    }
+
+    // The oldest behavior is dropped when the archive is full
+    pub fn insert(&mut self, descriptor: Vec<f32>, novelty: f32, fitness: &Fitness) {
+        if novelty < fitness.archive_threshold || fitness.archive_size == 0 {
+            return;
+        }
+        if self.descriptors.len() >= fitness.archive_size {
+            self.descriptors.remove(0);
+        }
+        self.descriptors.push(descriptor);
+    }
+}
+
+// Trajectory statistics of one life, every value of the descriptor is between 0 and 1
+#[derive(Clone)]
+pub struct Behavior {
+    visited: Vec<bool>,
+    steps: u64,
+    steps_near_mates: u64,
+    // Last position relative to the screen size
+    position: (f32, f32),
+}
+
+impl Behavior {
+    pub fn new() -> Self {
+        Behavior {
+            visited: Vec::new(),
+            steps: 0,
+            steps_near_mates: 0,
+            position: (0.0, 0.0),
+        }
+    }
+
+    pub fn record(&mut self, x: f32, y: f32, near_mates: bool, grid: usize) {
+        if self.visited.len() != grid * grid {
+            self.visited = vec![false; grid * grid];
+        }
+        let x = x.max(0.0).min(1.0);
+        let y = y.max(0.0).min(1.0);
+        let cell_x = ((x * grid as f32) as usize).min(grid - 1);
+        let cell_y = ((y * grid as f32) as usize).min(grid - 1);
+        self.visited[cell_y * grid + cell_x] = true;
+        self.steps += 1;
+        if near_mates {
+            self.steps_near_mates += 1;
+        }
+        self.position = (x, y);
+    }
+
+    // Final position, share of the covered grid cells and share of the steps spent near pack mates
+    pub fn descriptor(&self) -> Vec<f32> {
+        let covered = if self.visited.is_empty() {
+            0.0
+        } else {
+            self.visited.iter().filter(|&&visited| visited).count() as f32 / self.visited.len() as f32
+        };
+        let near_mates = if self.steps == 0 {
+            0.0
+        } else {
+            self.steps_near_mates as f32 / self.steps as f32
+        };
+        vec![self.position.0, self.position.1, covered, near_mates]
+    }
+}
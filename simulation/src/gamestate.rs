@@ -8,13 +8,27 @@ use ncollide2d::world::CollisionWorld;
 use serde_json::Value;
 use std::time::{Duration, Instant};
 
+use crate::archive::GenomeArchive;
+use crate::brain::{BrainNetwork, ACTIONS};
 use crate::collisionobjectdata::CollisionObjectData;
+use crate::crossover::Crossover;
+use crate::diversity::Diversity;
 use crate::entity::Entity;
+use crate::evolution::EvolutionMode;
+use crate::fitness::{FitnessMode, NoveltyArchive};
 use crate::food::Food;
+use crate::lineage::{DeathCause, Lineage};
+use crate::mutation::Mutation;
+use crate::pareto;
+use crate::placement::Placement;
 use crate::random_helper::RandomHelper;
-use crate::randomwalker::RandomWalker;
+use crate::randomwalker::{RandomWalker, RETIRED_GROUP};
+use crate::selection::{ElitismPolicy, Selection};
+use crate::ledger::{account_name, Ledger, Reason};
+use crate::sharing::{reputation, Hunt, Recipient, RelatednessMeasure, Share, SharingPolicy};
+use crate::species::{ReproductionMode, SpeciesConfig, NO_SPECIES, HERBIVORE_SPECIES, CARNIVORE_SPECIES_1, CARNIVORE_SPECIES_2, SPECIES_NAMES};
 use crate::wall::Wall;
-use crate::record::{GamestateRecord, EventRecord, AverageRecord, Writer};
+use crate::record::{GamestateRecord, EventRecord, AverageRecord, SignalRecord, GenerationRecord, GenomeRecord, PhylogenyEdgeRecord, DiversityRecord, ParetoRecord, PopulationRecord, SharingRecord, ReputationRecord, HuntRecord, HuntRecipientRecord, LedgerRecord, EnergyRecord, Writer};
 
 pub struct GameState {
     foods: Vec<Food>,
@@ -33,6 +47,16 @@ pub struct GameState {
     food_nutrition: i32,
     herbivore_nutrition: i32,
     threshold_herbivore_score: i32,
+    crossover: Crossover,
+    // Indexed by the species id
+    species_configs: Vec<SpeciesConfig>,
+    novelty_archives: Vec<NoveltyArchive>,
+    share_range: f32,
+    screen_size: (f32, f32),
+    evolution_mode: EvolutionMode,
+    episode_length: u64,
+    generation: u64,
+    generation_step: u64,
     sharing_percentage_1: f32,
     sharing_percentage_2: f32,
     start_recording: u64,
@@ -42,15 +66,54 @@ pub struct GameState {
     simulation_writer: Writer,
     event_writer: Writer,
     average_writer: Writer,
+    signal_writer: Writer,
+    generation_writer: Writer,
+    lineage: Lineage,
+    genome_writer: Writer,
+    phylogeny_writer: Writer,
+    diversity: Diversity,
+    diversity_writer: Writer,
+    pareto_interval: u64,
+    pareto_writer: Writer,
+    genome_archive: GenomeArchive,
+    population_writer: Writer,
+    sharing_interval: u64,
+    sharing_writer: Writer,
+    hunts_writer: Writer,
+    hunt_recipients_writer: Writer,
+    // Id of the next row of hunts.csv
+    next_hunt_id: u64,
+    reputation_interval: u64,
+    reputation_writer: Writer,
+    ledger: Ledger,
+    ledger_writer: Writer,
+    energy_writer: Writer,
+    // Agents born and died in the current step, indexed by the species id
+    births: [u64; 3],
+    deaths: [u64; 3],
+    // Id of the next agent created by a birth in the ecological mode
+    next_agent_id: i32,
+    seed: u64,
+    // Average scores of the last step, indexed by the species id
+    average_scores: [f32; 3],
+    output_prefix: Option<String>,
+    // Starts the console lines, the island of "island_1_" prints "[island_1] "
+    console_prefix: String,
     counter: u64,
     show_details: bool,
 }
 
 impl GameState {
     // Set up the initial state of the simulation
-    pub fn new(configs: (Value, Value)) -> Self {
+    // Output files are named with the prefix, islands use it to keep their files apart.
+    // Without a prefix nothing is written, which is used by the headless evaluations.
+    pub fn new(configs: (Value, Value), output_prefix: Option<&str>) -> Self {
         let config = configs.0;
         let wall_config = configs.1;
+        let writer = |name: &str| match output_prefix {
+            Some(prefix) => Writer::new(&(prefix.to_owned() + name)),
+            None => Writer::sink(),
+        };
 
         let food_amount = config["food_amount"].as_i64().unwrap() as i32;
         let herbivore_amount = config["herbivore_amount"].as_i64().unwrap() as i32;
@@ -70,11 +133,16 @@ impl GameState {
         let sharing_percentage_1 = config["sharing_percentage_1"].as_i64().unwrap() as i32;
         let sharing_percentage_2 = config["sharing_percentage_2"].as_i64().unwrap() as i32;
         let share_range = config["share_range"].as_f64().unwrap() as f32;
-        let mutation_rate = config["mutation_rate"].as_f64().unwrap() as f32;
+        let mutation = Mutation::from_config(&config);
+        let crossover = Crossover::from_config(&config);
+        let evolution_mode = EvolutionMode::from_name(config["evolution_mode"].as_str().unwrap());
+        let episode_length = config["episode_length"].as_u64().unwrap() as u64;
         let herbivore_size = config["herbivore_size"].as_f64().unwrap() as f32;
         let carnivore_size = config["carnivore_size"].as_f64().unwrap() as f32;
         let thinking_time = config["thinking_time"].as_i64().unwrap() as i32;
-        let view_range = config["view_range"].as_f64().unwrap() as f32;
+        let herbivore_species = SpeciesConfig::from_config(&config, &wall_config, "herbivore");
+        let carnivore_species_1 = SpeciesConfig::from_config(&config, &wall_config, "carnivore_1");
+        let carnivore_species_2 = SpeciesConfig::from_config(&config, &wall_config, "carnivore_2");
         let start_recording = config["start_recording"].as_u64().unwrap() as u64;
         let recording_duration = config["recording_duration"].as_u64().unwrap() as u64;
         let record_all_details = config["record_all_details"].as_bool().unwrap();
@@ -92,12 +160,13 @@ impl GameState {
         //WALL: 5
         //CARNIVORE RAY: 6
         //CARNIVORE ENVIRONMENT: 7
+        //RETIRED AGENT: 8
         food_group.set_membership(&[1]);
         food_group.set_whitelist(&[2, 4]);
         food_group.set_blacklist(&[1, 3, 5, 6, 7]);
         herbivore_group.set_membership(&[2]);
-        herbivore_group.set_whitelist(&[1, 3, 5, 6]);
-        herbivore_group.set_blacklist(&[2, 4, 7]);
+        herbivore_group.set_whitelist(&[1, 3, 4, 5, 6]);
+        herbivore_group.set_blacklist(&[2, 7]);
         carnivore_group.set_membership(&[3]);
         carnivore_group.set_whitelist(&[2, 4, 5, 6, 7]);
         carnivore_group.set_blacklist(&[1, 3]);
@@ -123,7 +192,7 @@ impl GameState {
         let mut walls = Vec::new();
         for _ in 0..food_amount {
             let (pos_x, pos_y) = random_helper.random_coordinate();
-            let coll_data = CollisionObjectData::new(Entity::FOOD, -2, None);
+            let coll_data = CollisionObjectData::new(Entity::FOOD, -2, NO_SPECIES, None);
             foods.push(Food::new(
                 world
                     .add(
@@ -137,9 +206,31 @@ impl GameState {
                     herbivore_size / 2.0
             ))
         }
+        // The walls come first so that no agent is placed inside them
+        for wall in wall_config["walls"].as_array().unwrap() {
+            let x1 = wall["x1"].as_f64().unwrap() as f32;
+            let y1 = wall["y1"].as_f64().unwrap() as f32;
+            let x2 = wall["x2"].as_f64().unwrap() as f32;
+            let y2 = wall["y2"].as_f64().unwrap() as f32;
+            let first = Point2::new(x1 * screen_size_x, y1 * screen_size_y);
+            let second = Point2::new(x2 * screen_size_x, y2 * screen_size_y);
+            let shape = ShapeHandle::new(Segment::new(first, second));
+            let coll_data = CollisionObjectData::new(Entity::WALL, -1, NO_SPECIES, None);
+            world.add(
+                Isometry2::new(Vector2::new(0.0, 0.0), zero()),
+                shape,
+                wall_group,
+                query,
+                coll_data.clone(),
+            );
+            walls.push(Wall::new(first, second));
+        }
+
         for i in 0..herbivore_amount {
-            let (pos_x, pos_y) = random_helper.random_coordinate();
-            let coll_data = CollisionObjectData::new(Entity::HERBIVORE, i, None);
+            let (pos_x, pos_y) = herbivore_species.placement.find_position(
+                &world, &mut random_helper, &omni_polygon, &herbivore_group, &[], None
+            );
+            let coll_data = CollisionObjectData::new(Entity::HERBIVORE, i, HERBIVORE_SPECIES, None);
             herbivores.push(RandomWalker::new(
                 world
                     .add(
@@ -156,18 +247,22 @@ impl GameState {
                 herbivore_speed,
                 initial_herbivore_health,
                 Entity::HERBIVORE,
+                HERBIVORE_SPECIES,
                 thinking_time,
-                view_range,
-                mutation_rate,
+                herbivore_species.clone(),
+                mutation.clone(),
+                share_range,
                 seed + i as u64 + 3333,
                 [1.0, 0.5, 0.0, 1.0],
                 [1.0, 0.5, 1.0, 1.0],
             ))
         }
         for i in 0..carnivore_amount_1 {
-            let (pos_x, pos_y) = random_helper.random_coordinate();
+            let (pos_x, pos_y) = carnivore_species_1.placement.find_position(
+                &world, &mut random_helper, &carni_polygon, &carnivore_group, &[], None
+            );
             let env_coll_data =
-                CollisionObjectData::new(Entity::OTHER, i, None);
+                CollisionObjectData::new(Entity::OTHER, i, CARNIVORE_SPECIES_1, None);
             let env_handle = Some(world
                 .add(
                     Isometry2::new(Vector2::new(pos_x, pos_y), zero()),
@@ -178,7 +273,7 @@ impl GameState {
                 )
                 .0);
             let coll_data =
-                CollisionObjectData::new(Entity::CARNIVORE, i, env_handle);
+                CollisionObjectData::new(Entity::CARNIVORE, i, CARNIVORE_SPECIES_1, env_handle);
             carnivores_1.push(RandomWalker::new(
                 world
                     .add(
@@ -195,18 +290,22 @@ impl GameState {
                 carnivore_speed,
                 initial_carnivore_health,
                 Entity::CARNIVORE,
+                CARNIVORE_SPECIES_1,
                 thinking_time,
-                view_range,
-                mutation_rate,
+                carnivore_species_1.clone(),
+                mutation.clone(),
+                share_range,
                 seed + i as u64 + 5555,
                 [1.0, 0.5, 0.5, 1.0],
                 [1.0, 1.0, 0.0, 1.0],
             ))
         }
         for i in 0..carnivore_amount_2 {
-            let (pos_x, pos_y) = random_helper.random_coordinate();
+            let (pos_x, pos_y) = carnivore_species_2.placement.find_position(
+                &world, &mut random_helper, &carni_polygon, &carnivore_group, &[], None
+            );
             let env_coll_data =
-                CollisionObjectData::new(Entity::OTHER, i + carnivore_amount_1, None);
+                CollisionObjectData::new(Entity::OTHER, i + carnivore_amount_1, CARNIVORE_SPECIES_2, None);
             let env_handle = Some(world
                 .add(
                     Isometry2::new(Vector2::new(pos_x, pos_y), zero()),
@@ -217,7 +316,7 @@ impl GameState {
                 )
                 .0);
             let coll_data =
-                CollisionObjectData::new(Entity::CARNIVORE, i + carnivore_amount_1, env_handle);
+                CollisionObjectData::new(Entity::CARNIVORE, i + carnivore_amount_1, CARNIVORE_SPECIES_2, env_handle);
             carnivores_2.push(RandomWalker::new(
                 world
                     .add(
@@ -234,31 +333,21 @@ impl GameState {
                 carnivore_speed,
                 initial_carnivore_health,
                 Entity::CARNIVORE,
+                CARNIVORE_SPECIES_2,
                 thinking_time,
-                view_range,
-                mutation_rate,
+                carnivore_species_2.clone(),
+                mutation.clone(),
+                share_range,
                 seed + carnivore_amount_1 as u64 + i as u64 + 5555,
                 [0.0, 0.3, 1.0, 1.0],
                 [0.0, 1.0, 1.0, 1.0],
             ))
         }
-        for wall in wall_config["walls"].as_array().unwrap() {
-            let x1 = wall["x1"].as_f64().unwrap() as f32;
-            let y1 = wall["y1"].as_f64().unwrap() as f32;
-            let x2 = wall["x2"].as_f64().unwrap() as f32;
-            let y2 = wall["y2"].as_f64().unwrap() as f32;
-            let first = Point2::new(x1 * screen_size_x, y1 * screen_size_y);
-            let second = Point2::new(x2 * screen_size_x, y2 * screen_size_y);
-            let shape = ShapeHandle::new(Segment::new(first, second));
-            let coll_data = CollisionObjectData::new(Entity::WALL, -1, None);
-            world.add(
-                Isometry2::new(Vector2::new(0.0, 0.0), zero()),
-                shape,
-                wall_group,
-                query,
-                coll_data.clone(),
-            );
-            walls.push(Wall::new(first, second));
+        // The initial agents are the roots of the phylogeny
+        let mut lineage = Lineage::new();
+        for walker in herbivores.iter_mut().chain(carnivores_1.iter_mut()).chain(carnivores_2.iter_mut()) {
+            let genome_id = lineage.birth(walker.get_species(), walker.get_id(), Vec::new(), 0);
+            walker.set_genome_id(genome_id);
         }
 
         GameState {
@@ -276,6 +365,15 @@ impl GameState {
             food_nutrition: food_nutrition,
             herbivore_nutrition: herbivore_nutrition,
             threshold_herbivore_score: threshold_herbivore_score,
+            crossover: crossover,
+            species_configs: vec![herbivore_species, carnivore_species_1, carnivore_species_2],
+            novelty_archives: vec![NoveltyArchive::new(); SPECIES_NAMES.len()],
+            share_range: share_range,
+            screen_size: (screen_size_x, screen_size_y),
+            evolution_mode: evolution_mode,
+            episode_length: episode_length,
+            generation: 0,
+            generation_step: 0,
             sharing_percentage_1: sharing_percentage_1 as f32 / 100.0,
             sharing_percentage_2: sharing_percentage_2 as f32 / 100.0,
             start: Instant::now(),
@@ -283,119 +381,1031 @@ impl GameState {
             recording_duration: recording_duration,
             record_all_details: record_all_details,
             recording: false,
-            simulation_writer: Writer::new("simulation.csv"),
-            event_writer: Writer::new("event.csv"),
-            average_writer: Writer::new("average.csv"),
+            simulation_writer: writer("simulation.csv"),
+            event_writer: writer("event.csv"),
+            average_writer: writer("average.csv"),
+            signal_writer: writer("signal.csv"),
+            generation_writer: writer("generation.csv"),
+            lineage: lineage,
+            genome_writer: writer("genomes.csv"),
+            phylogeny_writer: writer("phylogeny_edges.csv"),
+            diversity: Diversity::from_config(&config),
+            diversity_writer: writer("diversity.csv"),
+            pareto_interval: config["pareto_interval"].as_u64().unwrap() as u64,
+            pareto_writer: writer("pareto.csv"),
+            genome_archive: GenomeArchive::new(&config, output_prefix.map(|prefix| prefix.to_owned() + "genome_archive.jsonl").as_deref()),
+            average_scores: [0.0; 3],
+            population_writer: writer("population.csv"),
+            sharing_interval: config["sharing_interval"].as_u64().unwrap() as u64,
+            sharing_writer: writer("sharing.csv"),
+            hunts_writer: writer("hunts.csv"),
+            hunt_recipients_writer: writer("hunt_recipients.csv"),
+            next_hunt_id: 0,
+            reputation_interval: config["reputation_interval"].as_u64().unwrap() as u64,
+            reputation_writer: writer("reputation.csv"),
+            ledger: Ledger::new(&config),
+            ledger_writer: writer("ledger.csv"),
+            energy_writer: writer("energy_audit.csv"),
+            births: [0; 3],
+            deaths: [0; 3],
+            next_agent_id: std::cmp::max(herbivore_amount, carnivore_amount_1 + carnivore_amount_2),
+            seed: seed,
+            output_prefix: output_prefix.map(|prefix| prefix.to_owned()),
+            console_prefix: match output_prefix {
+                Some(prefix) if !prefix.is_empty() => format!("[{}] ", prefix.trim_end_matches('_')),
+                _ => String::new(),
+            },
             counter: 0,
             show_details: true,
         }
     }
 
-    fn handle_carnivore_herbivore_event(&self, carnivore: &CollisionObject<f32, CollisionObjectData>, herbivore: &CollisionObject<f32, CollisionObjectData>, sharing_percentage: f32) -> (bool, i32) {
-        let mut is_herbivore = false;
-        let mut hunt_counter = 0;
+    // Advances the simulation by one timestep
+    pub fn step(&mut self) {
+        let now = Instant::now();
+        self.counter += 1;
+        if self.counter % 1000 == 0 {
+            println!("{}Timestep: {}", self.console_prefix, self.counter);
+        }
+        if now - self.start >= Duration::from_secs(self.start_recording * 60 - 1)
+            && now - self.start
+                <= Duration::from_secs(self.start_recording * 60 + 1) || self.start_recording == 0 && self.counter == 1
+        {
+            println!("{}Recording started!", self.console_prefix);
+            println!("{}Timestep: {}", self.console_prefix, self.counter);
+        }
+        if now - self.start >= Duration::from_secs((self.start_recording + self.recording_duration) * 60 - 1)
+        && now - self.start
+        <= Duration::from_secs((self.start_recording + self.recording_duration) * 60 + 1)
+        {
+            println!("{}Recording stopped!", self.console_prefix);
+            println!("{}Timestep: {}", self.console_prefix, self.counter);
+        }
+        if now - self.start >= Duration::from_secs(self.start_recording * 60)
+            && now - self.start
+                <= Duration::from_secs((self.start_recording + self.recording_duration) * 60)
+        {
+            self.recording = true;
+        } else {
+            self.recording = false;
+        }
+
+        
+        // The energy of the initial agents opens the audit
+        if self.ledger.audit && self.ledger.balance().is_none() {
+            self.ledger.open(self.active_energy());
+        }
+        let (omni_score_avg, omni_health_avg, top_omni_score_avg, top_omni_health_avg) =
+            self.update_population(HERBIVORE_SPECIES);
+        let (carn1_score_avg, carn1_health_avg, top_carn1_score_avg, top_carn1_health_avg) =
+            self.update_population(CARNIVORE_SPECIES_1);
+        let (carn2_score_avg, carn2_health_avg, top_carn2_score_avg, top_carn2_health_avg) =
+            self.update_population(CARNIVORE_SPECIES_2);
+        for &species in [HERBIVORE_SPECIES, CARNIVORE_SPECIES_1, CARNIVORE_SPECIES_2].iter() {
+            self.update_fitness(species);
+            GameState::sort_by_fitness(self.population_mut(species));
+        }
+        
+        // The populations are sorted by fitness, which is not always the score
+        let best_score = |walkers: &Vec<RandomWalker>| walkers.iter().map(|walker| walker.get_score()).max().unwrap_or(0);
+        self.best_herbivore_score = best_score(&self.herbivores);
+        self.best_carnivore_score = std::cmp::max(best_score(&self.carnivores_1), best_score(&self.carnivores_2));
+
+        if self.diversity.is_due(self.counter) {
+            self.record_diversity();
+        }
+        if self.pareto_interval > 0 && self.counter % self.pareto_interval == 0 {
+            self.record_pareto_front();
+        }
+        if self.sharing_interval > 0 && self.counter % self.sharing_interval == 0 {
+            self.record_sharing_genes();
+        }
+        if self.reputation_interval > 0 && self.counter % self.reputation_interval == 0 {
+            self.record_reputations();
+        }
+        if self.genome_archive.is_due(self.counter) {
+            self.archive_genomes();
+        }
+        // Retired agents keep their genomes, they are parents of the next generation
+        if self.lineage.is_prune_due() {
+            let current: Vec<u64> = self.herbivores.iter().chain(self.carnivores_1.iter()).chain(self.carnivores_2.iter())
+                .map(|walker| walker.get_genome_id())
+                .collect();
+            self.lineage.prune(&current);
+        }
+
+        self.world.update();
+        self.handle_proximity_event();
+        for food in self.foods.iter_mut() {
+            food.update(&mut self.world, &mut self.random);
+        }
+        if self.evolution_mode == EvolutionMode::Generational {
+            self.generation_step += 1;
+            if self.generation_step >= self.episode_length || self.all_retired() {
+                self.next_generation();
+            }
+        }
+        self.world.update();
+        if self.ledger.audit {
+            self.audit_energy();
+        }
+
+        self.average_scores = [omni_score_avg, carn1_score_avg, carn2_score_avg];
+        let record = AverageRecord::new(
+            self.counter,
+            omni_score_avg,
+            carn1_score_avg,
+            carn2_score_avg,
+            omni_health_avg,
+            carn1_health_avg,
+            carn2_health_avg,
+            top_omni_score_avg,
+            top_carn1_score_avg,
+            top_carn2_score_avg,
+            top_omni_health_avg,
+            top_carn1_health_avg,
+            top_carn2_health_avg,
+        );
+        self.average_writer.write_average_record(record).unwrap();
+        for &species in [HERBIVORE_SPECIES, CARNIVORE_SPECIES_1, CARNIVORE_SPECIES_2].iter() {
+            let alive = self.population(species).iter().filter(|walker| walker.is_active()).count();
+            let record = PopulationRecord::new(
+                self.counter,
+                SPECIES_NAMES[species as usize],
+                alive as u64,
+                self.births[species as usize],
+                self.deaths[species as usize],
+            );
+            self.population_writer.write_population_record(record).unwrap();
+        }
+        self.births = [0; 3];
+        self.deaths = [0; 3];
+    }
+
+    pub fn set_show_details(&mut self, show_details: bool) {
+        self.show_details = show_details;
+    }
+
+    // The genomes still alive are written before the phylogeny is exported
+    pub fn export_lineage(&mut self) {
+        for genome_id in self.lineage.alive() {
+            GameState::write_genome(&mut self.genome_writer, &self.lineage, genome_id);
+        }
+        if let Some(prefix) = self.output_prefix.as_ref() {
+            self.lineage.write_newick(&(prefix.clone() + "phylogeny.nwk")).unwrap();
+        }
+    }
+
+    pub fn average_score(&self, species: i32) -> f32 {
+        self.average_scores[species as usize]
+    }
+
+    // Replaces the brains of a species with archived ones, reused in turn if there are fewer of them
+    pub fn load_genomes(&mut self, species: i32, genomes: &[Value]) {
+        if genomes.is_empty() {
+            return;
+        }
+        for (i, walker) in self.population_mut(species).iter_mut().enumerate() {
+            let mut brain = walker.get_brain();
+            brain.load_json(&genomes[i % genomes.len()]);
+            walker.set_brain(brain);
+        }
+    }
+
+    fn population(&self, species: i32) -> &Vec<RandomWalker> {
+        match species {
+            HERBIVORE_SPECIES => &self.herbivores,
+            CARNIVORE_SPECIES_1 => &self.carnivores_1,
+            _ => &self.carnivores_2,
+        }
+    }
+
+    pub fn population_size(&self, species: i32) -> usize {
+        self.population(species).len()
+    }
+
+    // Number of weights in the genomes of the species, None for an empty population
+    pub fn genome_size(&self, species: i32) -> Option<usize> {
+        self.population(species).first().map(|walker| walker.get_brain().weights().len())
+    }
+
+    // Copies of genomes picked by the species' selection strategy, with their genome ids
+    pub fn emigrants(&mut self, species: i32, count: usize) -> Vec<(BrainNetwork, u64)> {
+        let walkers = std::mem::replace(self.population_mut(species), Vec::new());
+        let fitness: Vec<f32> = walkers.iter().map(|walker| walker.get_fitness()).collect();
+        let mut emigrants = Vec::new();
+        let selection = &self.species_configs[species as usize].selection;
+        for _ in 0..count {
+            if let Some(index) = selection.select(&fitness, &mut self.random) {
+                emigrants.push((walkers[index].get_brain(), walkers[index].get_genome_id()));
+            }
+        }
+        *self.population_mut(species) = walkers;
+        emigrants
+    }
+
+    // The immigrants replace the agents with the lowest fitness and respawn unmutated, returns the
+    // genome ids they get. Immigrants beyond the population size are dropped.
+    pub fn immigrate(&mut self, species: i32, immigrants: Vec<BrainNetwork>) -> Vec<u64> {
+        let mut walkers = std::mem::replace(self.population_mut(species), Vec::new());
+        let mut genome_ids = Vec::new();
+        for (k, brain) in immigrants.into_iter().take(walkers.len()).enumerate() {
+            let i = walkers.len() - 1 - k;
+            if walkers[i].is_active() {
+                self.end_genome(&walkers[i], DeathCause::Replaced);
+            }
+            let placement = &self.species_configs[species as usize].placement;
+            let (x, y) = GameState::place(&self.world, &mut self.random, placement, &walkers[i], None);
+            walkers[i].respawn(&mut self.world, &self.ledger, x, y, false, brain);
+            self.start_genome(&mut walkers[i], Vec::new());
+            genome_ids.push(walkers[i].get_genome_id());
+        }
+        *self.population_mut(species) = walkers;
+        genome_ids
+    }
+
+    fn population_mut(&mut self, species: i32) -> &mut Vec<RandomWalker> {
+        match species {
+            HERBIVORE_SPECIES => &mut self.herbivores,
+            CARNIVORE_SPECIES_1 => &mut self.carnivores_1,
+            _ => &mut self.carnivores_2,
+        }
+    }
+
+    // Updates every agent of a species and replaces the dead ones, returns the average score and
+    // health of the whole population and of its top agents
+    fn update_population(&mut self, species: i32) -> (f32, f32, f32, f32) {
+        let mut walkers = std::mem::replace(self.population_mut(species), Vec::new());
+        let mut health_avg = 0.0;
+        let mut score_avg = 0.0;
+        let mut top_health_avg = 0.0;
+        let mut top_score_avg = 0.0;
+        let selection = &self.species_configs[species as usize].selection;
+        let elite_count = selection.elite_count(walkers.len());
+        let keep_elites = selection.elitism == ElitismPolicy::Keep;
+        // The statistics of the top agents need at least one agent
+        let top_count = elite_count.max(1).min(walkers.len());
+        // Fitness from the last sorting, the order the selection relies on
+        let fitness: Vec<f32> = walkers.iter().map(|walker| walker.get_fitness()).collect();
+        let energy_threshold = self.species_configs[species as usize].reproduction.energy_threshold;
+        let mut parents = Vec::new();
+        // Retired agents are left out of the averages
+        let mut active = 0;
+        for i in 0..walkers.len() {
+            if walkers[i].is_active() {
+                health_avg += walkers[i].get_health() as f32;
+                score_avg += walkers[i].get_score() as f32;
+                active += 1;
+            }
+            if !walkers[i].is_active() {
+                // Retired until the next generation
+            } else if walkers[i].is_dead(&self.world) {
+                // In generational mode the dead wait for the end of the episode, in ecological mode they stay dead
+                let cause = DeathCause::from_data(self.world.collision_object(walkers[i].get_handle()).unwrap().data());
+                self.end_genome(&walkers[i], cause);
+                if self.evolution_mode == EvolutionMode::SteadyState {
+                    let species_config = &self.species_configs[species as usize];
+                    let (brain, parents) = GameState::breed(&walkers, &fitness, &species_config.selection, &self.crossover, &mut self.random);
+                    let parent_position = GameState::position_of(&self.world, &walkers[parents[0]]);
+                    let (new_x, new_y) = GameState::place(&self.world, &mut self.random, &species_config.placement, &walkers[i], Some(parent_position));
+                    let mut parent_genomes: Vec<u64> = parents.iter().map(|&parent| walkers[parent].get_genome_id()).collect();
+                    let walker = &mut walkers[i];
+                    if i < elite_count && keep_elites {
+                        parent_genomes = vec![walker.get_genome_id()];
+                        let brain = walker.get_brain();
+                        walker.respawn(&mut self.world, &self.ledger, new_x, new_y, false, brain);
+                    } else {
+                        walker.respawn(&mut self.world, &self.ledger, new_x, new_y, true, brain);
+                    }
+                    self.start_genome(walker, parent_genomes);
+                } else {
+                    walkers[i].retire(&mut self.world, &self.ledger);
+                }
+            } else {
+                walkers[i].update(&mut self.world, &self.ledger);
+                if self.evolution_mode == EvolutionMode::Ecological && walkers[i].get_health() >= energy_threshold {
+                    parents.push(i);
+                }
+            }
+            let walker = &walkers[i];
+            if i < top_count {
+                top_health_avg += walker.get_health() as f32;
+                top_score_avg += walker.get_score() as f32;
+            }
+            if self.recording {
+                GameState::record_signal(&mut self.signal_writer, self.counter, walker, &self.world);
+            }
+            if self.recording && self.record_all_details {
+                let pos = self.world.collision_object(walker.get_handle()).unwrap().position();
+                let entity = walker.get_entity().to_string();
+                let record = GamestateRecord::new(
+                    self.counter,
+                    pos.translation.x,
+                    pos.translation.y,
+                    walker.get_id() as u64,
+                    walker.get_health(),
+                    walker.get_score(),
+                    &entity
+                );
+                self.simulation_writer.write_gamestate_record(record).unwrap();
+            }
+        }
+        match self.species_configs[species as usize].reproduction.mode {
+            ReproductionMode::Asexual => {
+                for parent in parents {
+                    self.give_birth(&mut walkers, &[parent]);
+                }
+            }
+            ReproductionMode::Mating => {
+                for (first, second) in self.find_mates(&walkers, &parents) {
+                    self.give_birth(&mut walkers, &[first, second]);
+                }
+            }
+        }
+        if walkers.len() > 0 {
+            top_health_avg /= top_count as f32;
+            top_score_avg /= top_count as f32;
+        }
+        if active > 0 {
+            health_avg /= active as f32;
+            score_avg /= active as f32;
+        }
+        *self.population_mut(species) = walkers;
+        (score_avg, health_avg, top_score_avg, top_health_avg)
+    }
+
+    // Pairs every candidate with the closest unpaired candidate within the mating radius
+    fn find_mates(&self, walkers: &[RandomWalker], candidates: &[usize]) -> Vec<(usize, usize)> {
+        let mut pairs = Vec::new();
+        let mut paired = vec![false; candidates.len()];
+        for a in 0..candidates.len() {
+            if paired[a] {
+                continue;
+            }
+            let species = walkers[candidates[a]].get_species() as usize;
+            let mating_radius = self.species_configs[species].reproduction.mating_radius;
+            let (x, y) = GameState::position_of(&self.world, &walkers[candidates[a]]);
+            let mut closest: Option<(usize, f32)> = None;
+            for b in (a + 1)..candidates.len() {
+                if paired[b] {
+                    continue;
+                }
+                let (other_x, other_y) = GameState::position_of(&self.world, &walkers[candidates[b]]);
+                let distance = ((x - other_x).powi(2) + (y - other_y).powi(2)).sqrt();
+                if distance <= mating_radius && closest.map_or(true, |(_, closest_distance)| distance < closest_distance) {
+                    closest = Some((b, distance));
+                }
+            }
+            if let Some((b, _)) = closest {
+                paired[a] = true;
+                paired[b] = true;
+                pairs.push((candidates[a], candidates[b]));
+            }
+        }
+        pairs
+    }
+
+    // A single parent gives half of its energy to a mutated copy of itself, two mates give a quarter
+    // each to a mutated crossover of their genomes. The offspring takes the place of a dead agent or
+    // joins the population as a new agent.
+    fn give_birth(&mut self, walkers: &mut Vec<RandomWalker>, parents: &[usize]) {
+        let parent = parents[0];
+        let species_config = &self.species_configs[walkers[parent].get_species() as usize];
+        if walkers.iter().filter(|walker| walker.is_active()).count() >= species_config.reproduction.max_population {
+            return;
+        }
+        let child = match walkers.iter().position(|walker| !walker.is_active()) {
+            Some(child) => child,
+            None => {
+                let seed = self.seed + self.next_agent_id as u64 + 7777;
+                let sibling = walkers[parent].sibling(&mut self.world, self.next_agent_id, species_config.clone(), seed);
+                self.next_agent_id += 1;
+                walkers.push(sibling);
+                walkers.len() - 1
+            }
+        };
+        let share = 0.5 / parents.len() as f32;
+        let parent_position = GameState::position_of(&self.world, &walkers[parent]);
+        let (x, y) = GameState::place(&self.world, &mut self.random, &species_config.placement, &walkers[child], Some(parent_position));
+        let brains: Vec<BrainNetwork> = parents.iter().map(|&parent| walkers[parent].get_brain()).collect();
+        let brain = self.crossover.combine(&brains, &mut self.random);
+        let parent_genomes = parents.iter().map(|&parent| walkers[parent].get_genome_id()).collect();
+        walkers[child].respawn(&mut self.world, &self.ledger, x, y, true, brain);
+        walkers[child].return_energy(&self.world, &self.ledger);
+        let offspring = walkers[child].get_handle();
+        for &parent in parents.iter() {
+            walkers[parent].give_energy(&self.world, &self.ledger, share, offspring);
+        }
+        walkers[child].refresh_health(&self.world);
+        self.start_genome(&mut walkers[child], parent_genomes);
+        if self.recording && parents.len() == 2 {
+            let record = EventRecord::new(
+                self.counter,
+                "mating",
+                walkers[parents[0]].get_id(),
+                walkers[parents[1]].get_id(),
+                walkers[parents[0]].get_entity().to_string(),
+                walkers[parents[1]].get_entity().to_string(),
+                x as u64,
+                y as u64,
+                walkers[parents[0]].get_score() as u64,
+                walkers[parents[1]].get_score() as u64,
+            );
+            self.event_writer.write_event_record(record).unwrap();
+        }
+    }
+
+    // Writes the distribution of the sharing gene over the living agents of every species where it evolves
+    fn record_sharing_genes(&mut self) {
+        for &species in [HERBIVORE_SPECIES, CARNIVORE_SPECIES_1, CARNIVORE_SPECIES_2].iter() {
+            if !self.species_configs[species as usize].sharing.evolvable {
+                continue;
+            }
+            let mut genes: Vec<f32> = self.population(species).iter()
+                .filter(|walker| walker.is_active())
+                .map(|walker| walker.get_brain().sharing)
+                .collect();
+            if genes.is_empty() {
+                continue;
+            }
+            genes.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            let mean = genes.iter().sum::<f32>() / genes.len() as f32;
+            let variance = genes.iter().map(|gene| (gene - mean).powi(2)).sum::<f32>() / genes.len() as f32;
+            let record = SharingRecord::new(
+                self.counter,
+                SPECIES_NAMES[species as usize],
+                genes.len() as u64,
+                mean,
+                variance.sqrt(),
+                genes[0],
+                genes[genes.len() / 2],
+                genes[genes.len() - 1],
+            );
+            self.sharing_writer.write_sharing_record(record).unwrap();
+        }
+    }
+
+    fn active_energy(&self) -> i64 {
+        [HERBIVORE_SPECIES, CARNIVORE_SPECIES_1, CARNIVORE_SPECIES_2].iter()
+            .flat_map(|&species| self.population(species).iter())
+            .filter(|walker| walker.is_active())
+            .map(|walker| self.world.collision_object(walker.get_handle()).unwrap().data().energy.get() as i64)
+            .sum()
+    }
+
+    // Checks that the energy of the active agents changed by exactly the transfers of the step and writes them.
+    // A failed audit stops the simulation once the step is written
+    fn audit_energy(&mut self) {
+        let audit = self.ledger.close(self.active_energy());
+        for transfer in audit.transfers.iter() {
+            let record = LedgerRecord::new(
+                self.counter,
+                account_name(transfer.source),
+                account_name(transfer.sink),
+                transfer.amount,
+                transfer.reason.name(),
+            );
+            self.ledger_writer.write_ledger_record(record).unwrap();
+        }
+        self.ledger_writer.flush().unwrap();
+        let record = EnergyRecord::new(self.counter, &audit);
+        self.energy_writer.write_energy_record(record).unwrap();
+        if audit.discrepancy() != 0 {
+            let message = format!("Energy audit failed at timestep {}: {} expected, {} found", self.counter, audit.expected, audit.total);
+            if self.ledger.strict {
+                panic!("{}{}", self.console_prefix, message);
+            }
+            println!("{}{}", self.console_prefix, message);
+        }
+    }
+
+    // Writes what the living carnivores of every group gave and received during their lives. Free riders received
+    // energy without ever catching prey themselves
+    fn record_reputations(&mut self) {
+        for &species in [CARNIVORE_SPECIES_1, CARNIVORE_SPECIES_2].iter() {
+            let mut population = 0;
+            let mut given = 0i64;
+            let mut received = 0i64;
+            let mut reputations = 0.0;
+            let mut free_riders = 0;
+            let mut free_rider_energy = 0i64;
+            let mut withheld = 0i64;
+            for walker in self.population(species).iter().filter(|walker| walker.is_active()) {
+                let data = self.world.collision_object(walker.get_handle()).unwrap().data();
+                population += 1;
+                given += data.given.get() as i64;
+                received += data.received.get() as i64;
+                reputations += reputation(data.given.get(), data.received.get());
+                withheld += data.withheld.get() as i64;
+                if data.received.get() > 0 && data.kills.get() == 0 {
+                    free_riders += 1;
+                    free_rider_energy += data.received.get() as i64;
+                }
+            }
+            if population == 0 {
+                continue;
+            }
+            let record = ReputationRecord::new(
+                self.counter,
+                SPECIES_NAMES[species as usize],
+                population,
+                given as f32 / population as f32,
+                received as f32 / population as f32,
+                reputations / population as f32,
+                free_riders,
+                free_rider_energy,
+                withheld,
+            );
+            self.reputation_writer.write_reputation_record(record).unwrap();
+        }
+    }
+
+    // Writes the genotype and behavior diversity of every species since the last report
+    fn record_diversity(&mut self) {
+        for &species in [HERBIVORE_SPECIES, CARNIVORE_SPECIES_1, CARNIVORE_SPECIES_2].iter() {
+            let mut walkers = std::mem::replace(self.population_mut(species), Vec::new());
+            let genomes: Vec<Vec<f32>> = walkers.iter().map(|walker| walker.get_brain().weights()).collect();
+            let mut action_counts = [0; ACTIONS];
+            for walker in walkers.iter_mut() {
+                for (total, count) in action_counts.iter_mut().zip(walker.take_action_counts().iter()) {
+                    *total += count;
+                }
+            }
+            let (shares, entropy) = Diversity::action_distribution(&action_counts);
+            let record = DiversityRecord::new(
+                self.counter,
+                SPECIES_NAMES[species as usize],
+                walkers.len() as u64,
+                Diversity::mean_pairwise_distance(&genomes),
+                self.diversity.clusters(&genomes) as u64,
+                shares[0],
+                shares[1],
+                shares[2],
+                entropy,
+            );
+            self.diversity_writer.write_diversity_record(record).unwrap();
+            *self.population_mut(species) = walkers;
+        }
+    }
+
+    // Writes the brains of the best agents of every species to the archive
+    fn archive_genomes(&mut self) {
+        for &species in [HERBIVORE_SPECIES, CARNIVORE_SPECIES_1, CARNIVORE_SPECIES_2].iter() {
+            let walkers = match species {
+                HERBIVORE_SPECIES => &self.herbivores,
+                CARNIVORE_SPECIES_1 => &self.carnivores_1,
+                _ => &self.carnivores_2,
+            };
+            for walker in walkers.iter().take(self.genome_archive.count) {
+                self.genome_archive.write(self.counter, self.generation, species, walker.get_genome_id(), &walker.get_brain()).unwrap();
+            }
+        }
+    }
+
+    // Records the behavior of the living agents and computes the fitness of every agent
+    // The populations are sorted by fitness, so the first agents are the best ones
+    fn update_fitness(&mut self, species: i32) {
+        let mut walkers = std::mem::replace(self.population_mut(species), Vec::new());
+        let fitness = &self.species_configs[species as usize].fitness;
+        if fitness.mode == FitnessMode::Score {
+            for walker in walkers.iter_mut() {
+                let score = walker.get_score();
+                walker.set_fitness(0.0, score as f32);
+            }
+        } else if fitness.mode == FitnessMode::Pareto {
+            let values: Vec<Vec<f32>> = walkers.iter().map(|walker| walker.get_objectives().values(&fitness.objectives)).collect();
+            let fronts = pareto::fronts(&values);
+            let crowding_distances = pareto::crowding_distances(&values, &fronts);
+            for (i, walker) in walkers.iter_mut().enumerate() {
+                walker.set_fitness(0.0, pareto::crowded_fitness(fronts[i], crowding_distances[i]));
+            }
+        } else {
+            let positions: Vec<(f32, f32)> = walkers.iter().map(|walker| GameState::position_of(&self.world, walker)).collect();
+            for i in 0..walkers.len() {
+                if !walkers[i].is_active() {
+                    continue;
+                }
+                let near_mates = (0..walkers.len()).any(|j| {
+                    j != i && walkers[j].is_active()
+                        && ((positions[i].0 - positions[j].0).powi(2) + (positions[i].1 - positions[j].1).powi(2)).sqrt() <= self.share_range
+                });
+                let (x, y) = (positions[i].0 / self.screen_size.0, positions[i].1 / self.screen_size.1);
+                walkers[i].behavior_mut().record(x, y, near_mates, fitness.grid);
+            }
+            let descriptors: Vec<Vec<f32>> = walkers.iter().map(|walker| walker.behavior().descriptor()).collect();
+            for (i, walker) in walkers.iter_mut().enumerate() {
+                let novelty = fitness.novelty(&descriptors[i], &descriptors, Some(i), &self.novelty_archives[species as usize]);
+                let score = walker.get_score();
+                walker.set_fitness(novelty, fitness.combine(score, novelty));
+            }
+        }
+        *self.population_mut(species) = walkers;
+    }
+
+    // Writes the objectives of the agents on the Pareto front of every species
+    fn record_pareto_front(&mut self) {
+        for &species in [HERBIVORE_SPECIES, CARNIVORE_SPECIES_1, CARNIVORE_SPECIES_2].iter() {
+            let walkers = std::mem::replace(self.population_mut(species), Vec::new());
+            let objectives = &self.species_configs[species as usize].fitness.objectives;
+            let values: Vec<Vec<f32>> = walkers.iter().map(|walker| walker.get_objectives().values(objectives)).collect();
+            let fronts = pareto::fronts(&values);
+            for (walker, front) in walkers.iter().zip(fronts.iter()) {
+                if *front > 0 {
+                    continue;
+                }
+                let objectives = walker.get_objectives();
+                let record = ParetoRecord::new(
+                    self.counter,
+                    SPECIES_NAMES[species as usize],
+                    walker.get_genome_id(),
+                    objectives.survival,
+                    objectives.energy_gained,
+                    objectives.energy_shared,
+                    objectives.hunts,
+                );
+                self.pareto_writer.write_pareto_record(record).unwrap();
+            }
+            *self.population_mut(species) = walkers;
+        }
+    }
+
+    fn sort_by_fitness(walkers: &mut Vec<RandomWalker>) {
+        walkers.sort_by(|a, b| b.get_fitness().partial_cmp(&a.get_fitness()).unwrap());
+    }
+
+    fn all_retired(&self) -> bool {
+        self.herbivores.iter()
+            .chain(self.carnivores_1.iter())
+            .chain(self.carnivores_2.iter())
+            .all(|walker| !walker.is_active())
+    }
+
+    // Ends the episode: writes the summary of every species and breeds a whole new population
+    fn next_generation(&mut self) {
+        for &species in [HERBIVORE_SPECIES, CARNIVORE_SPECIES_1, CARNIVORE_SPECIES_2].iter() {
+            let mut walkers = std::mem::replace(self.population_mut(species), Vec::new());
+            GameState::sort_by_fitness(&mut walkers);
+            if walkers.len() > 0 {
+                let score_sum: i32 = walkers.iter().map(|walker| walker.get_score()).sum();
+                let survivors = walkers.iter().filter(|walker| walker.is_active()).count();
+                let record = GenerationRecord::new(
+                    self.generation,
+                    self.counter,
+                    SPECIES_NAMES[species as usize],
+                    score_sum as f32 / walkers.len() as f32,
+                    walkers.iter().map(|walker| walker.get_score()).max().unwrap(),
+                    walkers.iter().map(|walker| walker.get_score()).min().unwrap(),
+                    survivors as u64,
+                );
+                self.generation_writer.write_generation_record(record).unwrap();
+            }
+            for walker in walkers.iter().filter(|walker| walker.is_active()) {
+                self.end_genome(walker, DeathCause::EpisodeEnd);
+            }
+            // Every offspring is bred before any agent respawns, because respawning replaces the parents' brains
+            let selection = &self.species_configs[species as usize].selection;
+            let elite_count = selection.elite_count(walkers.len());
+            let fitness: Vec<f32> = walkers.iter().map(|walker| walker.get_fitness()).collect();
+            let mut offspring = Vec::new();
+            for i in 0..walkers.len() {
+                let (brain, parents) = GameState::breed(&walkers, &fitness, selection, &self.crossover, &mut self.random);
+                let parent_position = GameState::position_of(&self.world, &walkers[parents[0]]);
+                if i < elite_count && selection.elitism == ElitismPolicy::Keep {
+                    offspring.push((walkers[i].get_brain(), false, parent_position, vec![walkers[i].get_genome_id()]));
+                } else {
+                    let parent_genomes = parents.iter().map(|&parent| walkers[parent].get_genome_id()).collect();
+                    offspring.push((brain, true, parent_position, parent_genomes));
+                }
+            }
+            for i in 0..walkers.len() {
+                let (brain, mutate, parent_position, parent_genomes) = offspring[i].clone();
+                let placement = &self.species_configs[species as usize].placement;
+                let (x, y) = GameState::place(&self.world, &mut self.random, placement, &walkers[i], Some(parent_position));
+                walkers[i].respawn(&mut self.world, &self.ledger, x, y, mutate, brain);
+                self.start_genome(&mut walkers[i], parent_genomes);
+            }
+            *self.population_mut(species) = walkers;
+        }
+        println!("{}Generation {} finished at timestep {}", self.console_prefix, self.generation, self.counter);
+        self.generation += 1;
+        self.generation_step = 0;
+    }
+
+    // Assembles an offspring genome from parents chosen by the selection strategy, returns it with the
+    // indices of the parents, the first one being the main parent. The population must not be empty.
+    fn breed(walkers: &[RandomWalker], fitness: &[f32], selection: &Selection, crossover: &Crossover, random: &mut RandomHelper) -> (BrainNetwork, Vec<usize>) {
+        let mut parents = Vec::new();
+        for _ in 0..crossover.parents {
+            parents.push(selection.select(fitness, random).expect("breeding needs a population"));
+        }
+        let brains: Vec<BrainNetwork> = parents.iter().map(|&parent| walkers[parent].get_brain()).collect();
+        (crossover.combine(&brains, random), parents)
+    }
+
+    // Closes the genome the agent carried and writes its record, a novel behavior is archived
+    fn end_genome(&mut self, walker: &RandomWalker, cause: DeathCause) {
+        let species = walker.get_species() as usize;
+        let fitness = &self.species_configs[species].fitness;
+        if fitness.mode == FitnessMode::Novelty || fitness.mode == FitnessMode::Combined {
+            self.novelty_archives[species].insert(walker.behavior().descriptor(), walker.get_novelty(), fitness);
+        }
+        self.lineage.death(walker.get_genome_id(), self.counter, cause);
+        self.deaths[species] += 1;
+        GameState::write_genome(&mut self.genome_writer, &self.lineage, walker.get_genome_id());
+    }
+
+    // Gives the agent a new genome descending from the parents, the main parent first
+    fn start_genome(&mut self, walker: &mut RandomWalker, parents: Vec<u64>) {
+        let species = walker.get_species();
+        let genome_id = self.lineage.birth(species, walker.get_id(), parents.clone(), self.counter);
+        for (rank, &parent) in parents.iter().enumerate() {
+            let record = PhylogenyEdgeRecord::new(parent, genome_id, rank, SPECIES_NAMES[species as usize], self.counter);
+            self.phylogeny_writer.write_phylogeny_edge_record(record).unwrap();
+        }
+        walker.set_genome_id(genome_id);
+        self.births[species as usize] += 1;
+    }
+
+    fn write_genome(writer: &mut Writer, lineage: &Lineage, genome_id: u64) {
+        let genome = lineage.genome(genome_id);
+        let parents: Vec<String> = genome.parents.iter().map(|parent| parent.to_string()).collect();
+        let cause = match genome.cause {
+            Some(cause) => cause.to_string(),
+            None => "alive".to_string(),
+        };
+        let record = GenomeRecord::new(
+            genome_id,
+            SPECIES_NAMES[genome.species as usize],
+            genome.agent_id,
+            parents.join(";"),
+            genome.birth,
+            genome.death,
+            cause,
+        );
+        writer.write_genome_record(record).unwrap();
+    }
+
+    fn position_of(world: &CollisionWorld<f32, CollisionObjectData>, walker: &RandomWalker) -> (f32, f32) {
+        let position = world.collision_object(walker.get_handle()).unwrap().position();
+        (position.translation.x, position.translation.y)
+    }
+
+    // Finds a respawn position for the agent, ignoring the agent's own collision objects
+    fn place(world: &CollisionWorld<f32, CollisionObjectData>, random: &mut RandomHelper, placement: &Placement,
+        walker: &RandomWalker, parent: Option<(f32, f32)>
+    ) -> (f32, f32) {
+        let mut own = vec![walker.get_handle()];
+        if let Some(env) = walker.get_env_handle() {
+            own.push(env);
+        }
+        let shape = world.collision_object(walker.get_handle()).unwrap().shape().clone();
+        placement.find_position(world, random, &shape, &walker.get_collision_groups(world), &own, parent)
+    }
+
+    fn record_signal(writer: &mut Writer, counter: u64, walker: &RandomWalker, world: &CollisionWorld<f32, CollisionObjectData>) {
+        if let Some((signal, prey_visible)) = walker.get_emitted_signal() {
+            let pos = world.collision_object(walker.get_handle()).unwrap().position();
+            let record = SignalRecord::new(
+                counter,
+                walker.get_id(),
+                SPECIES_NAMES[walker.get_species() as usize],
+                pos.translation.x,
+                pos.translation.y,
+                walker.get_health(),
+                *prey_visible,
+                signal[0],
+                if signal.len() > 1 { signal[1] } else { 0.0 },
+            );
+            writer.write_signal_record(record).unwrap();
+        }
+    }
+
+    // One row per caught herbivore in hunts.csv and one row per recipient of a share in hunt_recipients.csv
+    fn record_hunt(writer: &mut Writer, recipients_writer: &mut Writer, hunt_id: u64, counter: u64, hunter: &CollisionObject<f32, CollisionObjectData>,
+        prey: &CollisionObject<f32, CollisionObjectData>, hunt: &Hunt
+    ) {
+        let mean_relatedness = if hunt.shares.is_empty() {
+            0.0
+        } else {
+            hunt.shares.iter().map(|share| share.relatedness).sum::<f32>() / hunt.shares.len() as f32
+        };
+        let record = HuntRecord::new(
+            hunt_id,
+            counter,
+            hunter.data().id,
+            SPECIES_NAMES[hunter.data().species as usize],
+            prey.data().id,
+            prey.data().score.get(),
+            prey.position().translation.x,
+            prey.position().translation.y,
+            hunt.pack as u64,
+            hunt.nutrition,
+            hunt.kept,
+            hunt.lost,
+            hunt.shares.len() as u64,
+            mean_relatedness,
+        );
+        writer.write_hunt_record(record).unwrap();
+        for share in hunt.shares.iter() {
+            let record = HuntRecipientRecord::new(
+                hunt_id,
+                SPECIES_NAMES[share.species as usize],
+                share.id,
+                share.distance,
+                share.energy,
+                share.relatedness,
+            );
+            recipients_writer.write_hunt_recipient_record(record).unwrap();
+        }
+    }
+
+    fn walker_of(&self, object: &CollisionObject<f32, CollisionObjectData>) -> Option<&RandomWalker> {
+        if object.data().species == NO_SPECIES {
+            return None;
+        }
+        self.population(object.data().species).iter().find(|walker| walker.get_id() == object.data().id)
+    }
+
+    // Relatedness of the hunter to another carnivore measured the way the hunter's group is configured,
+    // carnivores of another species are unrelated
+    fn relatedness(&self, hunter: &CollisionObject<f32, CollisionObjectData>, other: &CollisionObject<f32, CollisionObjectData>) -> f32 {
+        if hunter.data().species != other.data().species {
+            return 0.0;
+        }
+        let (hunter, other) = match (self.walker_of(hunter), self.walker_of(other)) {
+            (Some(hunter), Some(other)) => (hunter, other),
+            _ => return 0.0,
+        };
+        let sharing = &self.species_configs[hunter.get_species() as usize].sharing;
+        match sharing.relatedness {
+            RelatednessMeasure::Genealogical => self.lineage.relatedness(hunter.get_genome_id(), other.get_genome_id(), sharing.relatedness_depth),
+            RelatednessMeasure::Genetic => {
+                let distance = Diversity::distance(&hunter.brain_weights(), &other.brain_weights());
+                (- distance / sharing.relatedness_scale).exp()
+            }
+        }
+    }
+
+    // The hunter's sharing gene, or the fixed sharing percentage of its group
+    fn sharing_percentage(&self, carnivore: &CollisionObject<f32, CollisionObjectData>) -> f32 {
+        let species = carnivore.data().species;
+        if self.species_configs[species as usize].sharing.evolvable {
+            carnivore.data().sharing.get()
+        } else if species == CARNIVORE_SPECIES_2 {
+            self.sharing_percentage_2
+        } else {
+            self.sharing_percentage_1
+        }
+    }
+
+    // Random number deciding whether a capture succeeds, only drawn when the prey is not always caught
+    fn capture_roll(species_configs: &[SpeciesConfig], random: &mut RandomHelper, prey: &CollisionObject<f32, CollisionObjectData>) -> f32 {
+        if prey.data().entity_type == Entity::HERBIVORE && species_configs[prey.data().species as usize].capture.is_random() {
+            random.random_between(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+
+    // Returns whether a herbivore was eaten, the number of carnivores that got a part of it, and the
+    // species, energy and relatedness to the hunter of every recipient
+    fn handle_carnivore_herbivore_event(&self, carnivore: &CollisionObject<f32, CollisionObjectData>, herbivore: &CollisionObject<f32, CollisionObjectData>, sharing_percentage: f32,
+        capture_roll: f32
+    ) -> Option<Hunt> {
+        let mut hunt = None;
         if herbivore.data().entity_type == Entity::HERBIVORE {
-            is_herbivore = true;
-            herbivore.data().eaten.set(true);
-            let added_nutrition = self.herbivore_nutrition as f32 * (herbivore.data().score.get() as f32 / self.threshold_herbivore_score as f32);
-            carnivore.data()
-                .energy
-                .set(carnivore.data().energy.get() + (added_nutrition * (1.0 - sharing_percentage)) as i32);
-            carnivore.data()
-                .fitness
-                .set(carnivore.data().fitness.get() + (added_nutrition * (1.0 - sharing_percentage)) as i32);
+            // The other living carnivores within the share range, the hunter's own body is not among them
+            let mut nearby = Vec::new();
             if let Some(handle) = carnivore.data().env_handle {
-                let interaction_results = self.world.interactions_with(handle, true);
-                if let Some(friends) = interaction_results {
-                    let mut friends_collection = Vec::new();
-                    for (_, (c1, c2, _)) in friends.enumerate() {
-                        friends_collection.push((c1, c2));
-                    }
-                    let friend_count = friends_collection.len();
-                    hunt_counter = friend_count + 1;
-                    let share = added_nutrition * sharing_percentage / friend_count as f32;
-                    for (c1, c2) in friends_collection {
-                        let friend = self.world.collision_object(c1).unwrap();
-                        if friend.data().id != carnivore.data().id {
-                            friend.data()
-                            .energy
-                            .set(friend.data().energy.get() + share as i32);
-                            friend.data()
-                            .fitness
-                            .set(friend.data().fitness.get() + share as i32);
+                if let Some(interactions) = self.world.interactions_with(handle, true) {
+                    for (c1, c2, _) in interactions {
+                        for &other in [c1, c2].iter() {
+                            let friend = self.world.collision_object(other).unwrap();
+                            if friend.data().entity_type == Entity::CARNIVORE && friend.data().id != carnivore.data().id
+                                && !friend.collision_groups().is_member_of(RETIRED_GROUP)
+                            {
+                                nearby.push(friend);
+                            }
                         }
-                        let friend = self.world.collision_object(c2).unwrap();
-                        if friend.data().id != carnivore.data().id {
-                            friend.data()
-                                .energy
-                                .set(friend.data().energy.get() + share as i32);
-                            friend.data()
-                                .fitness
-                                .set(friend.data().fitness.get() + share as i32);
-                        }
-                    }
-                    if friend_count == 0 {
-                        carnivore.data()
-                            .energy
-                            .set(carnivore.data().energy.get() + (added_nutrition * sharing_percentage) as i32);
-                        carnivore.data()
-                            .fitness
-                            .set(carnivore.data().fitness.get() + (added_nutrition * sharing_percentage) as i32);
                     }
                 }
             }
+            let pack = nearby.len() + 1;
+            let capture = &self.species_configs[herbivore.data().species as usize].capture;
+            if !capture.succeeds(pack, capture_roll) {
+                return None;
+            }
+            herbivore.data().eaten.set(true);
+            herbivore.data().killed_by.set(Some(Entity::CARNIVORE));
+            carnivore.data().hunts.set(carnivore.data().hunts.get() + 1);
+            carnivore.data().kills.set(carnivore.data().kills.get() + 1);
+            let nutrition = self.herbivore_nutrition as f32 * (herbivore.data().score.get() as f32 / self.threshold_herbivore_score as f32)
+                * capture.yield_factor(pack);
+            let added_nutrition = self.ledger.round(nutrition);
+            // The hunter gets the whole prey and passes the shares on
+            self.ledger.transfer(None, Some(carnivore.data()), added_nutrition, Reason::Hunt);
+            // The nearby carnivores that can get a share, those with a poor reputation are left out
+            let sharing = &self.species_configs[carnivore.data().species as usize].sharing;
+            let mut friends = Vec::new();
+            for friend in nearby.into_iter().filter(|friend| sharing.receives(carnivore.data().species, friend.data().species)) {
+                if sharing.withholds(friend.data().given.get(), friend.data().received.get()) {
+                    friend.data().withheld.set(friend.data().withheld.get() + 1);
+                } else {
+                    friends.push(friend);
+                }
+            }
+            let hunter_position = carnivore.position().translation.vector;
+            // The relatedness of every carnivore in range is only needed by the relatedness policy, otherwise
+            // it is measured for the recipients alone
+            let by_relatedness = sharing.policy == SharingPolicy::Relatedness;
+            let recipients: Vec<Recipient> = friends.iter().map(|friend| Recipient {
+                distance: (friend.position().translation.vector - hunter_position).norm(),
+                same_species: friend.data().species == carnivore.data().species,
+                chase: friend.data().chase.get(),
+                relatedness: if by_relatedness { self.relatedness(carnivore, friend) } else { 0.0 },
+            }).collect();
+            let (kept, parts) = sharing.divide(nutrition, sharing_percentage, &recipients, self.share_range);
+            let mut shares = Vec::new();
+            for &(i, share) in parts.iter() {
+                let (friend, recipient) = (&friends[i], &recipients[i]);
+                self.ledger.transfer(Some(carnivore.data()), Some(friend.data()), share, Reason::Share);
+                friend.data()
+                    .fitness
+                    .set(friend.data().fitness.get() + share);
+                friend.data().hunts.set(friend.data().hunts.get() + 1);
+                friend.data().received.set(friend.data().received.get() + share);
+                carnivore.data().shared.set(carnivore.data().shared.get() + share);
+                carnivore.data().given.set(carnivore.data().given.get() + share);
+                shares.push(Share {
+                    species: friend.data().species,
+                    id: friend.data().id,
+                    distance: recipient.distance,
+                    energy: share,
+                    relatedness: if by_relatedness { recipient.relatedness } else { self.relatedness(carnivore, friend) },
+                });
+            }
+            let lost = added_nutrition - kept - shares.iter().map(|share| share.energy).sum::<i32>();
+            self.ledger.transfer(Some(carnivore.data()), None, lost, Reason::Lost);
+            carnivore.data()
+                .fitness
+                .set(carnivore.data().fitness.get() + kept);
+            hunt = Some(Hunt {
+                pack: pack,
+                nutrition: added_nutrition,
+                kept: kept,
+                lost: lost,
+                shares: shares,
+            });
         }
-        return (is_herbivore, hunt_counter as i32);
+        return hunt;
     }
 
     fn handle_proximity_event(&mut self) {
         for event in self.world.proximity_events() {
             if event.new_status == Proximity::Intersecting {
-                let mut hunt_happened = false;
-                let mut hunt_count = 0;
                 let co1 = self.world.collision_object(event.collider1).unwrap();
                 let co2 = self.world.collision_object(event.collider2).unwrap();
                 match co1.data().entity_type {
                     Entity::FOOD => {
                         co1.data().eaten.set(true);
-                        co2.data()
-                            .energy
-                            .set(co2.data().energy.get() + self.food_nutrition);
+                        self.ledger.transfer(None, Some(co2.data()), self.food_nutrition, Reason::Food);
                         co2.data()
                             .fitness
                             .set(co2.data().fitness.get() + self.food_nutrition);
                     }
-                    Entity::WALL => co2.data().eaten.set(true),
+                    Entity::WALL => {
+                        co2.data().eaten.set(true);
+                        co2.data().killed_by.set(Some(Entity::WALL));
+                    }
                     Entity::CARNIVORE => {
-                        let mut sharing_percentage = self.sharing_percentage_1;
-                        if co1.data().id >= self.carnivores_1.len() as i32 {
-                            sharing_percentage = self.sharing_percentage_2;
+                        let sharing_percentage = self.sharing_percentage(co1);
+                        let roll = GameState::capture_roll(&self.species_configs, &mut self.random, co2);
+                        if let Some(hunt) = self.handle_carnivore_herbivore_event(co1, co2, sharing_percentage, roll) {
+                            GameState::record_hunt(&mut self.hunts_writer, &mut self.hunt_recipients_writer, self.next_hunt_id, self.counter, co1, co2, &hunt);
+                            self.next_hunt_id += 1;
                         }
-                        let result = self.handle_carnivore_herbivore_event(co1, co2, sharing_percentage);
-                        hunt_happened = result.0;
-                        hunt_count = result.1;
                     } 
                     _ => (),
                 }
                 match co2.data().entity_type {
                     Entity::FOOD => {
                         co2.data().eaten.set(true);
-                        co1.data()
-                            .energy
-                            .set(co1.data().energy.get() + self.food_nutrition);
+                        self.ledger.transfer(None, Some(co1.data()), self.food_nutrition, Reason::Food);
                         co1.data()
                             .fitness
                             .set(co1.data().fitness.get() + self.food_nutrition);
                     }
-                    Entity::WALL => co1.data().eaten.set(true),
+                    Entity::WALL => {
+                        co1.data().eaten.set(true);
+                        co1.data().killed_by.set(Some(Entity::WALL));
+                    }
                     Entity::CARNIVORE => {
-                        let mut sharing_percentage = self.sharing_percentage_1;
-                        if co2.data().id >= self.carnivores_1.len() as i32 {
-                            sharing_percentage = self.sharing_percentage_2;
+                        let sharing_percentage = self.sharing_percentage(co2);
+                        let roll = GameState::capture_roll(&self.species_configs, &mut self.random, co1);
+                        if let Some(hunt) = self.handle_carnivore_herbivore_event(co2, co1, sharing_percentage, roll) {
+                            GameState::record_hunt(&mut self.hunts_writer, &mut self.hunt_recipients_writer, self.next_hunt_id, self.counter, co2, co1, &hunt);
+                            self.next_hunt_id += 1;
                         }
-                        let result = self.handle_carnivore_herbivore_event(co2, co1, sharing_percentage);
-                        hunt_happened = result.0;
-                        hunt_count = result.1;
                     }
                     _ => (),
                 }
@@ -411,6 +1421,7 @@ impl GameState {
                     }
                     let record = EventRecord::new(
                         self.counter,
+                        "contact",
                         co1.data().id,
                         co2.data().id,
                         co1.data().entity_type.to_string(),
@@ -421,24 +1432,6 @@ impl GameState {
                         co2.data().score.get() as u64,
                     );
                     self.event_writer.write_event_record(record).unwrap();
-                    if hunt_happened {
-                        let mut omni = co1;
-                        if co2.data().entity_type == Entity::HERBIVORE {
-                            omni = co2;
-                        }
-                        let record = EventRecord::new(
-                            self.counter,
-                            -3,
-                            hunt_count,
-                            Entity::OTHER.to_string(),
-                            Entity::OTHER.to_string(),
-                            omni.position().translation.x as u64,
-                            omni.position().translation.y as u64,
-                            0,
-                            0,
-                        );
-                        self.event_writer.write_event_record(record).unwrap();
-                    }
                 }
             }
         }
@@ -457,247 +1450,7 @@ impl event::EventHandler for GameState {
             if input::keyboard::is_key_pressed(_ctx, KeyCode::S) {
                 self.show_details = true;
             }
-            self.counter += 1;
-            if self.counter % 1000 == 0 {
-                println!("Timestep: {}", self.counter);
-            }
-            if now - self.start >= Duration::from_secs(self.start_recording * 60 - 1)
-                && now - self.start
-                    <= Duration::from_secs(self.start_recording * 60 + 1) || self.start_recording == 0 && self.counter == 1
-            {
-                println!("Recording started!");
-                println!("Timestep: {}", self.counter);
-            }
-            if now - self.start >= Duration::from_secs((self.start_recording + self.recording_duration) * 60 - 1)
-            && now - self.start
-            <= Duration::from_secs((self.start_recording + self.recording_duration) * 60 + 1)
-            {
-                println!("Recording stopped!");
-                println!("Timestep: {}", self.counter);
-            }
-            if now - self.start >= Duration::from_secs(self.start_recording * 60)
-                && now - self.start
-                    <= Duration::from_secs((self.start_recording + self.recording_duration) * 60)
-            {
-                self.recording = true;
-            } else {
-                self.recording = false;
-            }
-
-            
-            let mut omni_health_avg = 0.0;
-            let mut omni_score_avg = 0.0;
-            let mut carn1_score_avg = 0.0;
-            let mut carn1_health_avg = 0.0;
-            let mut carn2_score_avg = 0.0;
-            let mut carn2_health_avg = 0.0;
-            let mut top_omni_health_avg = 0.0;
-            let mut top_omni_score_avg = 0.0;
-            let mut top_carn1_score_avg = 0.0;
-            let mut top_carn1_health_avg = 0.0;
-            let mut top_carn2_score_avg = 0.0;
-            let mut top_carn2_health_avg = 0.0;
-
-            let threshold = self.herbivores.len() / 10;
-            for i in 0..self.herbivores.len() {
-                let new_x;
-                let new_y;
-                {
-                    let new_pos = self.world.collision_object(
-                        self.herbivores[self.random.random_between(0.0, threshold as f32 + 0.1) as usize].get_handle()
-                    ).unwrap().position();
-                    let (x, y) = self.random.random_coordinate();
-                    new_x = (new_pos.translation.x * 2.0 + x) / 3.0;
-                    new_y = (new_pos.translation.y * 2.0 + y) / 3.0;
-                }
-                
-                let mut brain = self.herbivores[self.random.random_between(0.0, threshold as f32 + 0.1) as usize].get_brain();
-                let wall_brain = self.herbivores[self.random.random_between(0.0, threshold as f32 + 0.1) as usize].get_brain();
-                brain.wall_network = wall_brain.wall_network;
-                let carnivore_brain = self.herbivores[self.random.random_between(0.0, threshold as f32 + 0.1) as usize].get_brain();
-                brain.carnivore_network = carnivore_brain.carnivore_network;
-                let walker = & mut self.herbivores[i];
-                omni_health_avg += walker.get_health() as f32;
-                omni_score_avg += walker.get_score() as f32;
-                if walker.is_dead(&self.world) {
-                    if i < threshold {
-                        brain = walker.get_brain();
-                        walker.respawn(&mut self.world, new_x, new_y, false, brain);
-                    } else {
-                        walker.respawn(&mut self.world, new_x, new_y, true, brain);
-                    }
-                    
-                } else {
-                    walker.update(&mut self.world);
-                }
-                if i < threshold {
-                    top_omni_health_avg += walker.get_health() as f32;
-                    top_omni_score_avg += walker.get_score() as f32;
-                }
-                if self.recording && self.record_all_details {
-                    let pos = self.world.collision_object(walker.get_handle()).unwrap().position();
-                    let record = GamestateRecord::new(
-                        self.counter,
-                        pos.translation.x,
-                        pos.translation.y,
-                        walker.get_id() as u64,
-                        walker.get_health(),
-                        walker.get_score(),
-                        "HERBIVORE"
-                    );
-                    self.simulation_writer.write_gamestate_record(record).unwrap();
-                }
-            }
-            top_omni_health_avg /= threshold as f32;
-            top_omni_score_avg /= threshold as f32;
-            let threshold = self.carnivores_1.len() / 10;
-            for i in 0..self.carnivores_1.len() {
-                let new_x;
-                let new_y;
-                {
-                    let new_pos = self.world.collision_object(
-                        self.carnivores_1[self.random.random_between(0.0, threshold as f32 + 0.1) as usize].get_handle()
-                    ).unwrap().position();
-                    let (x, y) = self.random.random_coordinate();
-                    new_x = (new_pos.translation.x * 2.0 + x) / 3.0;
-                    new_y = (new_pos.translation.y * 2.0 + y) / 3.0;
-                }
-                let mut brain = self.carnivores_1[self.random.random_between(0.0, threshold as f32 + 0.1) as usize].get_brain();
-                let wall_brain = self.carnivores_1[self.random.random_between(0.0, threshold as f32 + 0.1) as usize].get_brain();
-                brain.wall_network = wall_brain.wall_network;
-                let carnivore_brain = self.carnivores_1[self.random.random_between(0.0, threshold as f32 + 0.1) as usize].get_brain();
-                brain.carnivore_network = carnivore_brain.carnivore_network;
-                let walker = &mut self.carnivores_1[i];
-                carn1_health_avg += walker.get_health() as f32;
-                carn1_score_avg += walker.get_score() as f32;
-                if walker.is_dead(&self.world) {
-                    if i < threshold {
-                        brain = walker.get_brain();
-                        walker.respawn(&mut self.world, new_x, new_y, false, brain);
-                    } else {
-                        walker.respawn(&mut self.world, new_x, new_y, true, brain);
-                    }
-                } else {
-                    walker.update(&mut self.world);
-                }
-                if i < threshold {
-                    top_carn1_health_avg += walker.get_health() as f32;
-                    top_carn1_score_avg += walker.get_score() as f32;
-                }
-                if self.recording && self.record_all_details {
-                    let pos = self.world.collision_object(walker.get_handle()).unwrap().position();
-                    let record = GamestateRecord::new(
-                        self.counter,
-                        pos.translation.x,
-                        pos.translation.y,
-                        walker.get_id() as u64,
-                        walker.get_health(),
-                        walker.get_score(),
-                        "CARNIVORE"
-                    );
-                    self.simulation_writer.write_gamestate_record(record).unwrap();
-                }
-            }
-            top_carn1_health_avg /= threshold as f32;
-            top_carn1_score_avg /= threshold as f32;
-            let threshold = self.carnivores_2.len() / 10;
-            for i in 0..self.carnivores_2.len() {
-                let new_x;
-                let new_y;
-                {
-                    let new_pos = self.world.collision_object(
-                        self.carnivores_2[self.random.random_between(0.0, threshold as f32 + 0.1) as usize].get_handle()
-                    ).unwrap().position();
-                    let (x, y) = self.random.random_coordinate();
-                    new_x = (new_pos.translation.x * 2.0 + x) / 3.0;
-                    new_y = (new_pos.translation.y * 2.0 + y) / 3.0;
-                }
-                let mut brain = self.carnivores_2[self.random.random_between(0.0, threshold as f32 + 0.1) as usize].get_brain();
-                let wall_brain = self.carnivores_2[self.random.random_between(0.0, threshold as f32 + 0.1) as usize].get_brain();
-                brain.wall_network = wall_brain.wall_network;
-                let carnivore_brain = self.carnivores_2[self.random.random_between(0.0, threshold as f32 + 0.1) as usize].get_brain();
-                brain.carnivore_network = carnivore_brain.carnivore_network;
-                let walker = &mut self.carnivores_2[i];
-                carn2_health_avg += walker.get_health() as f32;
-                carn2_score_avg += walker.get_score() as f32;
-                if walker.is_dead(&self.world) {
-                    if i < threshold {
-                        brain = walker.get_brain();
-                        walker.respawn(&mut self.world, new_x, new_y, false, brain);
-                    } else {
-                        walker.respawn(&mut self.world, new_x, new_y, true, brain);
-                    }
-                } else {
-                    walker.update(&mut self.world);
-                }
-                if i < threshold {
-                    top_carn2_health_avg += walker.get_health() as f32;
-                    top_carn2_score_avg += walker.get_score() as f32;
-                }
-                if self.recording && self.record_all_details {
-                    let pos = self.world.collision_object(walker.get_handle()).unwrap().position();
-                    let record = GamestateRecord::new(
-                        self.counter,
-                        pos.translation.x,
-                        pos.translation.y,
-                        walker.get_id() as u64,
-                        walker.get_health(),
-                        walker.get_score(),
-                        "CARNIVORE"
-                    );
-                    self.simulation_writer.write_gamestate_record(record).unwrap();
-                }
-            }
-            top_carn2_health_avg /= threshold as f32;
-            top_carn2_score_avg /= threshold as f32;
-            omni_health_avg /= self.herbivores.len() as f32;
-            omni_score_avg /= self.herbivores.len() as f32;
-            carn1_health_avg /= self.carnivores_1.len() as f32;
-            carn1_score_avg /= self.carnivores_1.len() as f32;
-            carn2_health_avg /= self.carnivores_2.len() as f32;
-            carn2_score_avg /= self.carnivores_2.len() as f32;
-            self.herbivores
-                .sort_by(|a, b| b.get_score().cmp(&a.get_score()));
-            self.carnivores_1
-                .sort_by(|a, b| b.get_score().cmp(&a.get_score()));
-            self.carnivores_2
-                .sort_by(|a, b| b.get_score().cmp(&a.get_score()));
-            
-            self.best_herbivore_score = 0;
-            self.best_carnivore_score = 0;
-            if self.herbivores.len() > 0 {
-                self.best_herbivore_score = self.herbivores[0].get_score();
-            }
-            if self.carnivores_1.len() > 0 {
-                self.best_carnivore_score = self.carnivores_1[0].get_score();
-            }
-            if self.carnivores_2.len() > 0 {
-                self.best_carnivore_score = std::cmp::max(self.best_carnivore_score, self.carnivores_2[0].get_score());
-            }
-
-            self.world.update();
-            self.handle_proximity_event();
-            for food in self.foods.iter_mut() {
-                food.update(&mut self.world, &mut self.random);
-            }
-            self.world.update();
-
-            let record = AverageRecord::new(
-                self.counter,
-                omni_score_avg,
-                carn1_score_avg,
-                carn2_score_avg,
-                omni_health_avg,
-                carn1_health_avg,
-                carn2_health_avg,
-                top_omni_score_avg,
-                top_carn1_score_avg,
-                top_carn2_score_avg,
-                top_omni_health_avg,
-                top_carn1_health_avg,
-                top_carn2_health_avg,
-            );
-            self.average_writer.write_average_record(record).unwrap();
+            self.step();
 
             // If we updated, we set our last_update to be now
             self.last_update = Instant::now();
@@ -705,6 +1458,11 @@ impl event::EventHandler for GameState {
         Ok(())
     }
 
+    fn quit_event(&mut self, _ctx: &mut Context) -> bool {
+        self.export_lineage();
+        false
+    }
+
     fn draw(&mut self, ctx: &mut Context) -> GameResult {
         graphics::clear(ctx, [0.0, 0.0, 0.0, 1.0].into());
         for wall in self.walls.iter_mut() {
@@ -715,27 +1473,33 @@ impl event::EventHandler for GameState {
         }
         let mut i = 0;
         let mut top = true;
-        let threshold = self.herbivores.len() / 10;
+        let threshold = self.species_configs[HERBIVORE_SPECIES as usize].selection.elite_count(self.herbivores.len());
         for walker in self.herbivores.iter_mut() {
             if i >= threshold {top = false}
             i += 1;
-            walker.draw(ctx, &mut self.world, top, self.show_details)?;
+            if walker.is_active() {
+                walker.draw(ctx, &mut self.world, top, self.show_details)?;
+            }
         }
         top = true;
         i = 0;
-        let threshold = self.carnivores_1.len() / 10;
+        let threshold = self.species_configs[CARNIVORE_SPECIES_1 as usize].selection.elite_count(self.carnivores_1.len());
         for walker in self.carnivores_1.iter_mut() {
             if i >= threshold {top = false}
             i += 1;
-            walker.draw(ctx, &mut self.world, top, self.show_details)?;
+            if walker.is_active() {
+                walker.draw(ctx, &mut self.world, top, self.show_details)?;
+            }
         }
         top = true;
         i = 0;
-        let threshold = self.carnivores_2.len() / 10;
+        let threshold = self.species_configs[CARNIVORE_SPECIES_2 as usize].selection.elite_count(self.carnivores_2.len());
         for walker in self.carnivores_2.iter_mut() {
             if i >= threshold {top = false}
             i += 1;
-            walker.draw(ctx, &mut self.world, top, self.show_details)?;
+            if walker.is_active() {
+                walker.draw(ctx, &mut self.world, top, self.show_details)?;
+            }
         }
         // Drawing the best score
         let omni_score = graphics::Text::new((
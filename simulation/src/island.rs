@@ -0,0 +1,171 @@
+use ggez::{event, input, Context, GameResult};
+use ggez::event::KeyCode;
+use serde_json::Value;
+use std::time::{Duration, Instant};
+
+use crate::brain::BrainNetwork;
+use crate::config::read_island_configs;
+use crate::gamestate::GameState;
+use crate::random_helper::RandomHelper;
+use crate::record::{MigrationRecord, Writer};
+use crate::species::{HERBIVORE_SPECIES, CARNIVORE_SPECIES_1, CARNIVORE_SPECIES_2, SPECIES_NAMES};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MigrationTopology {
+    // Island i sends migrants to island i + 1, the last one to the first
+    Ring,
+    // Every island sends migrants to every other island
+    FullyConnected,
+    // Every island sends migrants to one randomly chosen other island
+    Random,
+}
+
+impl MigrationTopology {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "ring" => MigrationTopology::Ring,
+            "fully_connected" => MigrationTopology::FullyConnected,
+            "random" => MigrationTopology::Random,
+            _ => panic!("Unknown migration topology: {}", name),
+        }
+    }
+}
+
+// Several isolated worlds stepped together, exchanging genomes from time to time
+pub struct Archipelago {
+    islands: Vec<GameState>,
+    // The island on the screen
+    shown: usize,
+    millis_per_update: u64,
+    last_update: Instant,
+    migration_interval: u64,
+    migration_rate: f32,
+    topology: MigrationTopology,
+    random: RandomHelper,
+    migration_writer: Writer,
+    counter: u64,
+}
+
+impl Archipelago {
+    pub fn new(config: &Value) -> Self {
+        let updates_per_second = config["updates_per_second"].as_f64().unwrap() as f32;
+        let screen_size_x = config["screen_size_x"].as_f64().unwrap() as f32;
+        let screen_size_y = config["screen_size_y"].as_f64().unwrap() as f32;
+        let seed = config["seed"].as_u64().unwrap() as u64;
+        let mut islands = Vec::new();
+        for (index, configs) in read_island_configs(config).unwrap().into_iter().enumerate() {
+            islands.push(GameState::new(configs, Some(&format!("island_{}_", index + 1))));
+        }
+        // Migrants keep their genome, so the brains have to have the same shape everywhere
+        for &species in [HERBIVORE_SPECIES, CARNIVORE_SPECIES_1, CARNIVORE_SPECIES_2].iter() {
+            let mut sizes: Vec<usize> = islands.iter().filter_map(|island| island.genome_size(species)).collect();
+            sizes.dedup();
+            assert!(sizes.len() <= 1, "The {} genomes differ between the islands", SPECIES_NAMES[species as usize]);
+        }
+        Archipelago {
+            islands: islands,
+            shown: 0,
+            millis_per_update: (1.0 / updates_per_second * 1000.0) as u64,
+            last_update: Instant::now(),
+            migration_interval: config["migration_interval"].as_u64().unwrap() as u64,
+            migration_rate: config["migration_rate"].as_f64().unwrap() as f32,
+            topology: MigrationTopology::from_name(config["migration_topology"].as_str().unwrap()),
+            random: RandomHelper::new(screen_size_x, screen_size_y, seed + 9999),
+            migration_writer: Writer::new("migration.csv"),
+            counter: 0,
+        }
+    }
+
+    fn targets(&mut self, source: usize) -> Vec<usize> {
+        let count = self.islands.len();
+        if count < 2 {
+            return Vec::new();
+        }
+        match self.topology {
+            MigrationTopology::Ring => vec![(source + 1) % count],
+            MigrationTopology::FullyConnected => (0..count).filter(|&target| target != source).collect(),
+            MigrationTopology::Random => {
+                let offset = (self.random.random_between(1.0, count as f32) as usize).max(1).min(count - 1);
+                vec![(source + offset) % count]
+            }
+        }
+    }
+
+    // Every island picks its emigrants before any immigrant arrives
+    fn migrate(&mut self) {
+        for &species in [HERBIVORE_SPECIES, CARNIVORE_SPECIES_1, CARNIVORE_SPECIES_2].iter() {
+            let mut departures: Vec<(usize, usize, BrainNetwork, u64)> = Vec::new();
+            for source in 0..self.islands.len() {
+                let count = (self.islands[source].population_size(species) as f32 * self.migration_rate).round() as usize;
+                if count == 0 {
+                    continue;
+                }
+                for target in self.targets(source) {
+                    for (brain, genome_id) in self.islands[source].emigrants(species, count) {
+                        departures.push((source, target, brain, genome_id));
+                    }
+                }
+            }
+            for target in 0..self.islands.len() {
+                let arrivals: Vec<&(usize, usize, BrainNetwork, u64)> =
+                    departures.iter().filter(|departure| departure.1 == target).collect();
+                let brains = arrivals.iter().map(|arrival| arrival.2.clone()).collect();
+                let genome_ids = self.islands[target].immigrate(species, brains);
+                for (arrival, genome_id) in arrivals.iter().zip(genome_ids.into_iter()) {
+                    let record = MigrationRecord::new(
+                        self.counter,
+                        SPECIES_NAMES[species as usize],
+                        arrival.0 as u64 + 1,
+                        target as u64 + 1,
+                        arrival.3,
+                        genome_id,
+                    );
+                    self.migration_writer.write_migration_record(record).unwrap();
+                }
+            }
+        }
+    }
+}
+
+impl event::EventHandler for Archipelago {
+    fn update(&mut self, ctx: &mut Context) -> GameResult {
+        let now = Instant::now();
+        if now - self.last_update >= Duration::from_millis(self.millis_per_update) {
+            let keys = [
+                KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4, KeyCode::Key5,
+                KeyCode::Key6, KeyCode::Key7, KeyCode::Key8, KeyCode::Key9,
+            ];
+            for (index, key) in keys.iter().enumerate() {
+                if index < self.islands.len() && input::keyboard::is_key_pressed(ctx, *key) {
+                    self.shown = index;
+                }
+            }
+            for island in self.islands.iter_mut() {
+                if input::keyboard::is_key_pressed(ctx, KeyCode::H) {
+                    island.set_show_details(false);
+                }
+                if input::keyboard::is_key_pressed(ctx, KeyCode::S) {
+                    island.set_show_details(true);
+                }
+                island.step();
+            }
+            self.counter += 1;
+            if self.migration_interval > 0 && self.counter % self.migration_interval == 0 {
+                self.migrate();
+            }
+            self.last_update = Instant::now();
+        }
+        Ok(())
+    }
+
+    fn quit_event(&mut self, _ctx: &mut Context) -> bool {
+        for island in self.islands.iter_mut() {
+            island.export_lineage();
+        }
+        false
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        event::EventHandler::draw(&mut self.islands[self.shown], ctx)
+    }
+}
@@ -0,0 +1,236 @@
+use serde_json::Value;
+use std::cell::{Cell, RefCell};
+
+use crate::collisionobjectdata::CollisionObjectData;
+use crate::species::SPECIES_NAMES;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Reason {
+    // The initial energy of an agent that is born or respawned
+    Birth,
+    // One unit burnt by every agent in every step
+    Metabolism,
+    Food,
+    // The nutrition of a caught herbivore
+    Hunt,
+    // Part of a prey passed on by the hunter
+    Share,
+    // Energy a parent gives to its offspring
+    Offspring,
+    // Energy above the maximum, it is cut off
+    ClampLoss,
+    // Energy left when an agent dies, retires or is replaced
+    Death,
+    // The part of a prey nobody gets with the equal sharing policy
+    Lost,
+    // The initial energy an offspring gives back, it lives on its parents' energy instead
+    Returned,
+}
+
+impl Reason {
+    pub const ALL: [Reason; 10] = [
+        Reason::Birth, Reason::Metabolism, Reason::Food, Reason::Hunt, Reason::Share, Reason::Offspring, Reason::ClampLoss,
+        Reason::Death, Reason::Lost, Reason::Returned,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Reason::Birth => "birth",
+            Reason::Metabolism => "metabolism",
+            Reason::Food => "food",
+            Reason::Hunt => "hunt",
+            Reason::Share => "share",
+            Reason::Offspring => "offspring",
+            Reason::ClampLoss => "clamp_loss",
+            Reason::Death => "death",
+            Reason::Lost => "lost",
+            Reason::Returned => "returned",
+        }
+    }
+}
+
+// An agent as species and id, None is the environment
+pub type Account = Option<(i32, i32)>;
+
+pub fn account_name(account: Account) -> String {
+    match account {
+        Some((species, id)) => format!("{}:{}", SPECIES_NAMES[species as usize], id),
+        None => String::from("environment"),
+    }
+}
+
+#[derive(Clone)]
+pub struct Transfer {
+    pub source: Account,
+    pub sink: Account,
+    pub amount: i32,
+    pub reason: Reason,
+}
+
+// The transfers since the last audit and the energy of the active agents before and after them
+pub struct Audit {
+    pub transfers: Vec<Transfer>,
+    // Energy moved for every reason
+    pub moved: Vec<(Reason, i64)>,
+    pub expected: i64,
+    pub total: i64,
+    pub rounding_loss: f32,
+}
+
+impl Audit {
+    pub fn discrepancy(&self) -> i64 {
+        self.total - self.expected
+    }
+
+    pub fn moved(&self, reason: Reason) -> i64 {
+        self.moved.iter().find(|(moved_reason, _)| *moved_reason == reason).map_or(0, |(_, amount)| *amount)
+    }
+}
+
+// Every change of the agents' energy goes through the ledger. In audit mode the transfers are kept
+// until the end of the step, when the energy of the active agents is checked against them.
+pub struct Ledger {
+    pub audit: bool,
+    // A failed audit stops the simulation instead of printing a warning
+    pub strict: bool,
+    transfers: RefCell<Vec<Transfer>>,
+    // Nutrition lost by rounding to whole energy units since the last audit
+    rounding_loss: Cell<f32>,
+    // Energy of the active agents after the last audit, None before the first step
+    balance: Cell<Option<i64>>,
+}
+
+impl Ledger {
+    pub fn new(config: &Value) -> Self {
+        Ledger {
+            audit: config["energy_audit"].as_bool().unwrap(),
+            strict: config["energy_audit_strict"].as_bool().unwrap(),
+            transfers: RefCell::new(Vec::new()),
+            rounding_loss: Cell::new(0.0),
+            balance: Cell::new(None),
+        }
+    }
+
+    // Moves energy from the source to the sink, None is the environment
+    pub fn transfer(&self, source: Option<&CollisionObjectData>, sink: Option<&CollisionObjectData>, amount: i32, reason: Reason) {
+        if amount == 0 {
+            return;
+        }
+        if let Some(source) = source {
+            source.energy.set(source.energy.get() - amount);
+        }
+        if let Some(sink) = sink {
+            sink.energy.set(sink.energy.get() + amount);
+        }
+        if self.audit {
+            self.transfers.borrow_mut().push(Transfer {
+                source: source.map(|data| (data.species, data.id)),
+                sink: sink.map(|data| (data.species, data.id)),
+                amount: amount,
+                reason: reason,
+            });
+        }
+    }
+
+    // Whole energy units of a nutrition, the fraction is lost
+    pub fn round(&self, amount: f32) -> i32 {
+        let whole = amount as i32;
+        if self.audit {
+            self.rounding_loss.set(self.rounding_loss.get() + amount - whole as f32);
+        }
+        whole
+    }
+
+    pub fn balance(&self) -> Option<i64> {
+        self.balance.get()
+    }
+
+    pub fn open(&self, total: i64) {
+        self.balance.set(Some(total));
+    }
+
+    // Compares the energy of the active agents with the last balance and the transfers since, the
+    // total found becomes the new balance
+    pub fn close(&self, total: i64) -> Audit {
+        let transfers: Vec<Transfer> = self.transfers.borrow_mut().drain(..).collect();
+        let moved = Reason::ALL.iter()
+            .map(|&reason| (reason, transfers.iter().filter(|transfer| transfer.reason == reason).map(|transfer| transfer.amount as i64).sum()))
+            .collect();
+        let mut expected = self.balance.get().unwrap_or(total);
+        for transfer in transfers.iter() {
+            if transfer.source.is_some() {
+                expected -= transfer.amount as i64;
+            }
+            if transfer.sink.is_some() {
+                expected += transfer.amount as i64;
+            }
+        }
+        let rounding_loss = self.rounding_loss.replace(0.0);
+        self.balance.set(Some(total));
+        Audit {
+            transfers: transfers,
+            moved: moved,
+            expected: expected,
+            total: total,
+            rounding_loss: rounding_loss,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    use crate::entity::Entity;
+
+    fn ledger(audit: bool) -> Ledger {
+        Ledger::new(&json!({ "energy_audit": audit, "energy_audit_strict": false }))
+    }
+
+    #[test]
+    fn transfer_moves_energy() {
+        let ledger = ledger(false);
+        let hunter = CollisionObjectData::new(Entity::CARNIVORE, 1, 1, None);
+        let mate = CollisionObjectData::new(Entity::CARNIVORE, 2, 1, None);
+        ledger.transfer(None, Some(&hunter), 100, Reason::Hunt);
+        ledger.transfer(Some(&hunter), Some(&mate), 30, Reason::Share);
+        assert_eq!(hunter.energy.get(), 470);
+        assert_eq!(mate.energy.get(), 430);
+        // Without the audit nothing is kept
+        assert!(ledger.close(0).transfers.is_empty());
+    }
+
+    #[test]
+    fn audit_balances_the_transfers() {
+        let ledger = ledger(true);
+        let hunter = CollisionObjectData::new(Entity::CARNIVORE, 1, 1, None);
+        let mate = CollisionObjectData::new(Entity::CARNIVORE, 2, 1, None);
+        ledger.open(800);
+        let nutrition = ledger.round(100.5);
+        ledger.transfer(None, Some(&hunter), nutrition, Reason::Hunt);
+        ledger.transfer(Some(&hunter), Some(&mate), 30, Reason::Share);
+        ledger.transfer(Some(&mate), None, 1, Reason::Metabolism);
+        ledger.transfer(Some(&mate), None, 0, Reason::Death);
+        let audit = ledger.close(hunter.energy.get() as i64 + mate.energy.get() as i64);
+        assert_eq!(audit.transfers.len(), 3);
+        assert_eq!(audit.expected, 899);
+        assert_eq!(audit.discrepancy(), 0);
+        assert_eq!(audit.moved(Reason::Share), 30);
+        assert_eq!(audit.moved(Reason::Death), 0);
+        assert_eq!(audit.rounding_loss, 0.5);
+        assert_eq!(ledger.balance(), Some(899));
+    }
+
+    #[test]
+    fn audit_finds_untracked_energy() {
+        let ledger = ledger(true);
+        let agent = CollisionObjectData::new(Entity::HERBIVORE, 1, 0, None);
+        ledger.open(400);
+        agent.energy.set(agent.energy.get() + 5);
+        let audit = ledger.close(agent.energy.get() as i64);
+        assert_eq!(audit.discrepancy(), 5);
+        // The next audit starts from the energy found
+        assert_eq!(ledger.close(405).discrepancy(), 0);
+    }
+}
@@ -0,0 +1,258 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::Write;
+
+use crate::collisionobjectdata::CollisionObjectData;
+use crate::entity::Entity;
+use crate::species::SPECIES_NAMES;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DeathCause {
+    Eaten,
+    Wall,
+    Starved,
+    // Still alive when the generation ended
+    EpisodeEnd,
+    // Replaced by an immigrant from another island
+    Replaced,
+}
+
+impl DeathCause {
+    pub fn from_data(data: &CollisionObjectData) -> Self {
+        if !data.eaten.get() {
+            DeathCause::Starved
+        } else if data.killed_by.get() == Some(Entity::WALL) {
+            DeathCause::Wall
+        } else {
+            DeathCause::Eaten
+        }
+    }
+}
+
+impl fmt::Display for DeathCause {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            DeathCause::Eaten => "eaten",
+            DeathCause::Wall => "wall",
+            DeathCause::Starved => "starved",
+            DeathCause::EpisodeEnd => "episode_end",
+            DeathCause::Replaced => "replaced",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+pub struct Genome {
+    pub species: i32,
+    // Id of the agent that carried the genome
+    pub agent_id: i32,
+    // Genome ids of the parents, the first one is the main parent
+    pub parents: Vec<u64>,
+    pub birth: u64,
+    pub death: Option<u64>,
+    pub cause: Option<DeathCause>,
+}
+
+// The genomes by genome id. Pruning keeps the genomes of the current agents and their ancestors on
+// the main parent lines, the records of the others are in genomes.csv.
+pub struct Lineage {
+    genomes: BTreeMap<u64, Genome>,
+    next_id: u64,
+    // Number of genomes kept by the last pruning, the next one is due when it has doubled
+    pruned_size: usize,
+}
+
+impl Lineage {
+    pub fn new() -> Self {
+        Lineage {
+            genomes: BTreeMap::new(),
+            next_id: 0,
+            pruned_size: 0,
+        }
+    }
+
+    pub fn birth(&mut self, species: i32, agent_id: i32, parents: Vec<u64>, timestep: u64) -> u64 {
+        let genome_id = self.next_id;
+        self.genomes.insert(genome_id, Genome {
+            species: species,
+            agent_id: agent_id,
+            parents: parents,
+            birth: timestep,
+            death: None,
+            cause: None,
+        });
+        self.next_id += 1;
+        genome_id
+    }
+
+    pub fn death(&mut self, genome_id: u64, timestep: u64, cause: DeathCause) {
+        let genome = self.genomes.get_mut(&genome_id).unwrap();
+        genome.death = Some(timestep);
+        genome.cause = Some(cause);
+    }
+
+    pub fn genome(&self, genome_id: u64) -> &Genome {
+        &self.genomes[&genome_id]
+    }
+
+    pub fn is_prune_due(&self) -> bool {
+        self.genomes.len() >= 2 * self.pruned_size.max(1024)
+    }
+
+    // Drops the genomes that are not on the main parent line of one of the current genomes. The
+    // genomes of dead agents that can still become parents have to be among the current ones.
+    pub fn prune(&mut self, current: &[u64]) {
+        let mut kept = HashSet::new();
+        for &id in current.iter() {
+            let mut ancestor = Some(id);
+            while let Some(id) = ancestor {
+                if !kept.insert(id) {
+                    break;
+                }
+                ancestor = self.genomes.get(&id).and_then(|genome| genome.parents.first().cloned());
+            }
+        }
+        self.genomes.retain(|id, _| kept.contains(id));
+        self.pruned_size = self.genomes.len();
+    }
+
+    // 0.5 to the power of the number of births separating the genomes through their most recent common
+    // ancestor on the main parent lines: 1 for the same genome, 0.5 for parent and child, 0.25 for
+    // siblings. Genomes without a common ancestor within depth generations are unrelated.
+    pub fn relatedness(&self, first: u64, second: u64, depth: usize) -> f32 {
+        let mut ancestors = Vec::new();
+        let mut genome = Some(first);
+        while let Some(id) = genome {
+            if ancestors.len() > depth {
+                break;
+            }
+            ancestors.push(id);
+            genome = self.genomes.get(&id).and_then(|genome| genome.parents.first().cloned());
+        }
+        let mut genome = Some(second);
+        let mut generations = 0;
+        while let Some(id) = genome {
+            if generations > depth {
+                break;
+            }
+            if let Some(first_generations) = ancestors.iter().position(|&ancestor| ancestor == id) {
+                return 0.5_f32.powi((first_generations + generations) as i32);
+            }
+            generations += 1;
+            genome = self.genomes.get(&id).and_then(|genome| genome.parents.first().cloned());
+        }
+        0.0
+    }
+
+    // Genome ids of the genomes that have not died yet
+    pub fn alive(&self) -> Vec<u64> {
+        self.genomes.iter().filter(|(_, genome)| genome.death.is_none()).map(|(&id, _)| id).collect()
+    }
+
+    // The tree of a species following the main parents, with the genomes as labeled nodes and the
+    // timesteps between births as branch lengths. The initial genomes hang from a common root.
+    pub fn newick(&self, species: i32) -> String {
+        let mut children: HashMap<u64, Vec<u64>> = HashMap::new();
+        let mut roots = Vec::new();
+        for (&id, genome) in self.genomes.iter() {
+            if genome.species != species {
+                continue;
+            }
+            match genome.parents.first() {
+                Some(&parent) if self.genomes.contains_key(&parent) => children.entry(parent).or_default().push(id),
+                _ => roots.push(id),
+            }
+        }
+        let no_children = Vec::new();
+        // Built without recursion, long runs produce lines of descent deeper than the stack
+        let mut tree = String::from("(");
+        for (i, &root) in roots.iter().enumerate() {
+            if i > 0 {
+                tree.push(',');
+            }
+            let mut stack = vec![(root, 0)];
+            while let Some((node, next)) = stack.pop() {
+                let node_children = children.get(&node).unwrap_or(&no_children);
+                if next == 0 && !node_children.is_empty() {
+                    tree.push('(');
+                }
+                if next < node_children.len() {
+                    if next > 0 {
+                        tree.push(',');
+                    }
+                    stack.push((node, next + 1));
+                    stack.push((node_children[next], 0));
+                } else {
+                    if !node_children.is_empty() {
+                        tree.push(')');
+                    }
+                    let genome = &self.genomes[&node];
+                    let length = match genome.parents.first().and_then(|parent| self.genomes.get(parent)) {
+                        Some(parent) => genome.birth - parent.birth,
+                        None => 0,
+                    };
+                    tree.push_str(&format!("g{}:{}", node, length));
+                }
+            }
+        }
+        tree.push_str(&format!("){};", SPECIES_NAMES[species as usize]));
+        tree
+    }
+
+    // One tree per species and line
+    pub fn write_newick(&self, filename: &str) -> Result<(), Box<dyn Error>> {
+        let mut file = File::create(filename)?;
+        for species in 0..SPECIES_NAMES.len() {
+            writeln!(file, "{}", self.newick(species as i32))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 0 and 1 are initial genomes, 2 and 3 children of 0, 4 a child of 2
+    fn lineage() -> Lineage {
+        let mut lineage = Lineage::new();
+        lineage.birth(0, 0, Vec::new(), 0);
+        lineage.birth(0, 1, Vec::new(), 0);
+        lineage.birth(0, 2, vec![0, 1], 10);
+        lineage.birth(0, 3, vec![0], 20);
+        lineage.birth(0, 4, vec![2], 30);
+        lineage
+    }
+
+    #[test]
+    fn relatedness_follows_the_main_parents() {
+        let lineage = lineage();
+        assert_eq!(lineage.relatedness(2, 2, 8), 1.0);
+        assert_eq!(lineage.relatedness(2, 0, 8), 0.5);
+        assert_eq!(lineage.relatedness(0, 4, 8), 0.25);
+        assert_eq!(lineage.relatedness(2, 3, 8), 0.25);
+        assert_eq!(lineage.relatedness(4, 3, 8), 0.125);
+        // 1 is only a second parent
+        assert_eq!(lineage.relatedness(2, 1, 8), 0.0);
+        assert_eq!(lineage.relatedness(4, 3, 1), 0.0);
+    }
+
+    #[test]
+    fn prune_keeps_the_lines_of_the_current_genomes() {
+        let mut lineage = lineage();
+        for genome_id in 0..4 {
+            lineage.death(genome_id, 40, DeathCause::Starved);
+        }
+        lineage.prune(&[4]);
+        assert_eq!(lineage.alive(), vec![4]);
+        assert_eq!(lineage.relatedness(4, 0, 8), 0.25);
+        assert_eq!(lineage.newick(0), "(((g4:20)g2:10)g0:0)herbivore;");
+    }
+
+    #[test]
+    fn newick_of_every_genome() {
+        assert_eq!(lineage().newick(0), "(((g4:20)g2:10,g3:20)g0:0,g1:0)herbivore;");
+    }
+}
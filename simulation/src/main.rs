@@ -3,6 +3,7 @@ use ggez::{event, GameResult};
 #[macro_use]
 extern crate serde_derive;
 use crate::gamestate::GameState;
+use crate::island::Archipelago;
 use crate::config::read_config_from_file;
 mod gamestate;
 mod food;
@@ -12,12 +13,36 @@ mod collisionobjectdata;
 mod random_helper;
 mod config;
 mod brain;
+mod crossover;
+mod evolution;
+mod mutation;
 mod wall;
 mod record;
+mod species;
+mod selection;
+mod placement;
+mod lineage;
+mod diversity;
+mod island;
+mod fitness;
+mod pareto;
+mod archive;
+mod ciao;
+mod sharing;
+mod capture;
+mod ledger;
 
 
 fn main() -> GameResult {
 
+    // `ciao <archive>` evaluates the archived genomes against each other without opening a window
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() > 1 && args[1] == "ciao" {
+        let archive = args.get(2).map(|archive| archive.as_str()).unwrap_or("genome_archive.jsonl");
+        ciao::run(archive).unwrap();
+        return Ok(());
+    }
+
     let configs = read_config_from_file().unwrap();
 
     // Setup metadata
@@ -30,8 +55,14 @@ fn main() -> GameResult {
             configs.0["screen_size_y"].as_f64().unwrap() as f32))
         .build()?;
 
-    // New instance of the simulation
-    let state = &mut GameState::new(configs);
-    // Run the simulation
-    event::run(ctx, events_loop, state)
+    if configs.0["islands"].as_array().unwrap().is_empty() {
+        // New instance of the simulation
+        let state = &mut GameState::new(configs, Some(""));
+        // Run the simulation
+        event::run(ctx, events_loop, state)
+    } else {
+        // The islands are built from the base parameters, only the window uses them directly
+        let state = &mut Archipelago::new(&configs.0);
+        event::run(ctx, events_loop, state)
+    }
 }
\ No newline at end of file
@@ -0,0 +1,78 @@
+use serde_json::Value;
+
+use crate::random_helper::RandomHelper;
+
+// Smallest step size a self-adaptive genome can shrink to
+const MIN_MUTATION_SIGMA: f32 = 0.0001;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MutationOperator {
+    // Adds N(0, sigma) noise to the weight
+    Gaussian,
+    // Replaces the weight with a fresh uniform value in [-1, 1]
+    Uniform,
+    // Replaces exactly one randomly chosen weight of one sub-network (the original scheme)
+    Single,
+}
+
+impl MutationOperator {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "gaussian" => MutationOperator::Gaussian,
+            "uniform" => MutationOperator::Uniform,
+            "single" => MutationOperator::Single,
+            _ => panic!("Unknown mutation operator: {}", name),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Mutation {
+    // Probability that an offspring's genome is mutated at all
+    pub rate: f32,
+    // Probability that a single weight is changed, once the genome is being mutated
+    pub weight_rate: f32,
+    pub operator: MutationOperator,
+    pub sigma: f32,
+    // Weights are clamped to [-limit, limit], None means unbounded weights
+    pub weight_limit: Option<f32>,
+    // The step size is stored in the genome and evolves with it
+    pub self_adaptive: bool,
+    pub learning_rate: f32,
+}
+
+impl Mutation {
+    pub fn from_config(config: &Value) -> Self {
+        Mutation {
+            rate: config["mutation_rate"].as_f64().unwrap() as f32,
+            weight_rate: config["weight_mutation_rate"].as_f64().unwrap() as f32,
+            operator: MutationOperator::from_name(config["mutation_operator"].as_str().unwrap()),
+            sigma: config["mutation_sigma"].as_f64().unwrap() as f32,
+            weight_limit: config["weight_limit"].as_f64().map(|limit| limit as f32),
+            self_adaptive: config["self_adaptive_mutation"].as_bool().unwrap(),
+            learning_rate: config["mutation_learning_rate"].as_f64().unwrap() as f32,
+        }
+    }
+
+    // Returns the new value of a weight that was selected for mutation
+    pub fn perturb(&self, weight: f32, sigma: f32, rand: &mut RandomHelper) -> f32 {
+        let new_weight = match self.operator {
+            MutationOperator::Gaussian => weight + rand.random_gaussian(0.0, sigma),
+            MutationOperator::Uniform | MutationOperator::Single => rand.random_between(-1.0, 1.0),
+        };
+        self.clamp(new_weight)
+    }
+
+    pub fn clamp(&self, weight: f32) -> f32 {
+        match self.weight_limit {
+            Some(limit) => weight.max(-limit).min(limit),
+            None => weight,
+        }
+    }
+
+    // Log-normal self-adaptation of the step size: sigma' = sigma * exp(tau * N(0, 1))
+    pub fn adapt_sigma(&self, sigma: f32, rand: &mut RandomHelper) -> f32 {
+        let new_sigma = sigma * (self.learning_rate * rand.random_gaussian(0.0, 1.0)).exp();
+        new_sigma.max(MIN_MUTATION_SIGMA)
+    }
+}
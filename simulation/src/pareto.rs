@@ -0,0 +1,156 @@
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Objective {
+    // Steps survived in the current life
+    Survival,
+    // Energy gained from food, prey and shares
+    EnergyGained,
+    // Energy given to pack mates
+    EnergyShared,
+    // Hunts the agent made or received a share of
+    Hunts,
+}
+
+impl Objective {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "survival" => Objective::Survival,
+            "energy_gained" => Objective::EnergyGained,
+            "energy_shared" => Objective::EnergyShared,
+            "hunts" => Objective::Hunts,
+            _ => panic!("Unknown objective: {}", name),
+        }
+    }
+}
+
+// Every objective is maximized
+#[derive(Clone, Default)]
+pub struct Objectives {
+    pub survival: u64,
+    pub energy_gained: i64,
+    pub energy_shared: i64,
+    pub hunts: u64,
+}
+
+impl Objectives {
+    pub fn value(&self, objective: Objective) -> f32 {
+        match objective {
+            Objective::Survival => self.survival as f32,
+            Objective::EnergyGained => self.energy_gained as f32,
+            Objective::EnergyShared => self.energy_shared as f32,
+            Objective::Hunts => self.hunts as f32,
+        }
+    }
+
+    pub fn values(&self, objectives: &[Objective]) -> Vec<f32> {
+        objectives.iter().map(|&objective| self.value(objective)).collect()
+    }
+}
+
+fn dominates(first: &[f32], second: &[f32]) -> bool {
+    first.iter().zip(second.iter()).all(|(a, b)| a >= b) && first.iter().zip(second.iter()).any(|(a, b)| a > b)
+}
+
+// Non-dominated sorting: the index of the Pareto front of every agent, 0 is the non-dominated front
+pub fn fronts(values: &[Vec<f32>]) -> Vec<usize> {
+    let count = values.len();
+    let mut dominated_by = vec![0; count];
+    let mut dominates_list = vec![Vec::new(); count];
+    for i in 0..count {
+        for j in 0..count {
+            if i != j && dominates(&values[i], &values[j]) {
+                dominates_list[i].push(j);
+                dominated_by[j] += 1;
+            }
+        }
+    }
+    let mut fronts = vec![0; count];
+    let mut current: Vec<usize> = (0..count).filter(|&i| dominated_by[i] == 0).collect();
+    let mut front = 0;
+    while !current.is_empty() {
+        let mut next = Vec::new();
+        for &i in current.iter() {
+            fronts[i] = front;
+            for &j in dominates_list[i].iter() {
+                dominated_by[j] -= 1;
+                if dominated_by[j] == 0 {
+                    next.push(j);
+                }
+            }
+        }
+        current = next;
+        front += 1;
+    }
+    fronts
+}
+
+// Crowding distance of every agent within its front, the extremes of every objective are infinitely far
+pub fn crowding_distances(values: &[Vec<f32>], fronts: &[usize]) -> Vec<f32> {
+    let mut distances = vec![0.0; values.len()];
+    let objective_count = values.first().map(|value| value.len()).unwrap_or(0);
+    let front_count = fronts.iter().max().map(|front| front + 1).unwrap_or(0);
+    for front in 0..front_count {
+        let members: Vec<usize> = (0..values.len()).filter(|&i| fronts[i] == front).collect();
+        for objective in 0..objective_count {
+            let mut sorted = members.clone();
+            sorted.sort_by(|&a, &b| values[a][objective].partial_cmp(&values[b][objective]).unwrap());
+            let first = sorted[0];
+            let last = sorted[sorted.len() - 1];
+            let range = values[last][objective] - values[first][objective];
+            distances[first] = std::f32::INFINITY;
+            distances[last] = std::f32::INFINITY;
+            if range <= 0.0 {
+                continue;
+            }
+            for k in 1..sorted.len().saturating_sub(1) {
+                distances[sorted[k]] += (values[sorted[k + 1]][objective] - values[sorted[k - 1]][objective]) / range;
+            }
+        }
+    }
+    distances
+}
+
+// A single value ordering the agents like the crowded comparison of NSGA-II: a lower front first,
+// within a front the larger crowding distance first
+pub fn crowded_fitness(front: usize, crowding_distance: f32) -> f32 {
+    let crowding = if crowding_distance.is_infinite() {
+        0.999
+    } else {
+        0.99 * crowding_distance / (1.0 + crowding_distance)
+    };
+    - (front as f32) + crowding
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fronts_rank_by_domination() {
+        let values = vec![vec![3.0, 3.0], vec![1.0, 4.0], vec![2.0, 2.0], vec![1.0, 1.0], vec![3.0, 3.0]];
+        assert_eq!(fronts(&values), vec![0, 0, 1, 2, 0]);
+    }
+
+    #[test]
+    fn fronts_of_nothing() {
+        assert!(fronts(&[]).is_empty());
+    }
+
+    #[test]
+    fn crowding_distances_within_a_front() {
+        let values = vec![vec![0.0, 4.0], vec![1.0, 3.0], vec![3.0, 1.0], vec![4.0, 0.0], vec![0.0, 0.0]];
+        let fronts = fronts(&values);
+        assert_eq!(fronts, vec![0, 0, 0, 0, 1]);
+        let distances = crowding_distances(&values, &fronts);
+        assert!(distances[0].is_infinite() && distances[3].is_infinite());
+        // (3 - 0) / 4 for both objectives
+        assert!((distances[1] - 1.5).abs() < 1e-6);
+        assert!((distances[2] - 1.5).abs() < 1e-6);
+        assert!(distances[4].is_infinite());
+    }
+
+    #[test]
+    fn crowded_fitness_prefers_the_lower_front() {
+        assert!(crowded_fitness(0, 0.0) > crowded_fitness(1, std::f32::INFINITY));
+        assert!(crowded_fitness(1, 2.0) > crowded_fitness(1, 1.0));
+    }
+}
@@ -0,0 +1,193 @@
+use nalgebra::{zero, Isometry2, Vector2};
+use ncollide2d::pipeline::object::{CollisionGroups, CollisionObjectSlabHandle};
+use ncollide2d::query;
+use ncollide2d::shape::ShapeHandle;
+use ncollide2d::world::CollisionWorld;
+use serde_json::Value;
+
+use crate::collisionobjectdata::CollisionObjectData;
+use crate::entity::Entity;
+use crate::random_helper::RandomHelper;
+use crate::randomwalker::RETIRED_GROUP;
+use crate::species::{species_id, HERBIVORE_SPECIES};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PlacementStrategy {
+    // Anywhere on the map
+    Uniform,
+    // Between the parent's position and a random point, parent_weight of the way toward the parent
+    NearParent,
+    // Inside one of the species' spawn regions of the map
+    SpawnRegions,
+    // The candidate farthest from the closest predator
+    FarFromPredators,
+    // Within nest_radius of the species' nest
+    Nest,
+}
+
+impl PlacementStrategy {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "uniform" => PlacementStrategy::Uniform,
+            "near_parent" => PlacementStrategy::NearParent,
+            "spawn_regions" => PlacementStrategy::SpawnRegions,
+            "far_from_predators" => PlacementStrategy::FarFromPredators,
+            "nest" => PlacementStrategy::Nest,
+            _ => panic!("Unknown placement strategy: {}", name),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Placement {
+    pub strategy: PlacementStrategy,
+    pub parent_weight: f32,
+    pub candidates: usize,
+    // Nest center in pixels
+    pub nest: (f32, f32),
+    pub nest_radius: f32,
+    // Rectangles (x1, y1, x2, y2) in pixels
+    pub spawn_regions: Vec<(f32, f32, f32, f32)>,
+    // Number of rejected positions before giving up on the strategy
+    pub attempts: usize,
+    pub species: i32,
+}
+
+impl Placement {
+    pub fn from_config(config: &Value, wall_config: &Value, species: &Value, name: &str) -> Self {
+        let screen_size_x = config["screen_size_x"].as_f64().unwrap() as f32;
+        let screen_size_y = config["screen_size_y"].as_f64().unwrap() as f32;
+        let strategy = PlacementStrategy::from_name(species["strategy"].as_str().unwrap());
+        let nest = species["nest"].as_array().unwrap();
+        let mut spawn_regions = Vec::new();
+        if let Some(regions) = wall_config["spawn_regions"][name].as_array() {
+            for region in regions {
+                spawn_regions.push((
+                    region["x1"].as_f64().unwrap() as f32 * screen_size_x,
+                    region["y1"].as_f64().unwrap() as f32 * screen_size_y,
+                    region["x2"].as_f64().unwrap() as f32 * screen_size_x,
+                    region["y2"].as_f64().unwrap() as f32 * screen_size_y,
+                ));
+            }
+        }
+        if strategy == PlacementStrategy::SpawnRegions && spawn_regions.is_empty() {
+            panic!("The map has no spawn regions for {}", name);
+        }
+        Placement {
+            strategy: strategy,
+            parent_weight: species["parent_weight"].as_f64().unwrap() as f32,
+            candidates: (species["candidates"].as_u64().unwrap() as usize).max(1),
+            nest: (
+                nest[0].as_f64().unwrap() as f32 * screen_size_x,
+                nest[1].as_f64().unwrap() as f32 * screen_size_y,
+            ),
+            nest_radius: species["nest_radius"].as_f64().unwrap() as f32,
+            spawn_regions: spawn_regions,
+            attempts: (config["placement_attempts"].as_u64().unwrap() as usize).max(1),
+            species: species_id(name),
+        }
+    }
+
+    // Finds a position where the agent does not touch a wall or a body that would kill it. The shape
+    // and groups are the agent's own, its own collision objects are ignored. When the strategy keeps
+    // failing, uniform positions are tried before the last candidate is accepted anyway.
+    pub fn find_position(&self, world: &CollisionWorld<f32, CollisionObjectData>, random: &mut RandomHelper,
+        shape: &ShapeHandle<f32>, groups: &CollisionGroups, own: &[CollisionObjectSlabHandle], parent: Option<(f32, f32)>
+    ) -> (f32, f32) {
+        let mut position = random.random_coordinate();
+        for _ in 0..self.attempts {
+            position = self.candidate(world, random, parent);
+            if Placement::is_clear(world, shape, groups, own, position) {
+                return position;
+            }
+        }
+        for _ in 0..self.attempts {
+            position = random.random_coordinate();
+            if Placement::is_clear(world, shape, groups, own, position) {
+                return position;
+            }
+        }
+        position
+    }
+
+    fn candidate(&self, world: &CollisionWorld<f32, CollisionObjectData>, random: &mut RandomHelper, parent: Option<(f32, f32)>) -> (f32, f32) {
+        match self.strategy {
+            PlacementStrategy::Uniform => random.random_coordinate(),
+            PlacementStrategy::NearParent => {
+                let (x, y) = random.random_coordinate();
+                match parent {
+                    Some((parent_x, parent_y)) => (
+                        parent_x * self.parent_weight + x * (1.0 - self.parent_weight),
+                        parent_y * self.parent_weight + y * (1.0 - self.parent_weight),
+                    ),
+                    None => (x, y),
+                }
+            }
+            PlacementStrategy::SpawnRegions => {
+                let index = (random.random_between(0.0, self.spawn_regions.len() as f32) as usize).min(self.spawn_regions.len() - 1);
+                let (x1, y1, x2, y2) = self.spawn_regions[index];
+                let x = x1.min(x2) + random.random_between(0.0, 1.0) * (x2 - x1).abs();
+                let y = y1.min(y2) + random.random_between(0.0, 1.0) * (y2 - y1).abs();
+                random.clamp_coordinate(x, y)
+            }
+            PlacementStrategy::FarFromPredators => {
+                let predators = self.predator_positions(world);
+                let mut best = random.random_coordinate();
+                let mut best_distance = Placement::closest_distance(&predators, best);
+                for _ in 1..self.candidates {
+                    let candidate = random.random_coordinate();
+                    let distance = Placement::closest_distance(&predators, candidate);
+                    if distance > best_distance {
+                        best = candidate;
+                        best_distance = distance;
+                    }
+                }
+                best
+            }
+            PlacementStrategy::Nest => {
+                // The square root spreads the positions evenly over the disk
+                let radius = self.nest_radius * random.random_between(0.0, 1.0).sqrt();
+                let angle = random.random_between(0.0, 2.0 * std::f32::consts::PI);
+                random.clamp_coordinate(self.nest.0 + radius * angle.cos(), self.nest.1 + radius * angle.sin())
+            }
+        }
+    }
+
+    // Only herbivores are hunted, the carnivores have no predators and are placed uniformly
+    fn predator_positions(&self, world: &CollisionWorld<f32, CollisionObjectData>) -> Vec<(f32, f32)> {
+        let mut positions = Vec::new();
+        if self.species == HERBIVORE_SPECIES {
+            for (_, object) in world.collision_objects() {
+                if object.data().entity_type == Entity::CARNIVORE && !object.collision_groups().is_member_of(RETIRED_GROUP) {
+                    positions.push((object.position().translation.x, object.position().translation.y));
+                }
+            }
+        }
+        positions
+    }
+
+    fn closest_distance(positions: &[(f32, f32)], position: (f32, f32)) -> f32 {
+        positions.iter()
+            .map(|(x, y)| ((x - position.0).powi(2) + (y - position.1).powi(2)).sqrt())
+            .fold(std::f32::MAX, f32::min)
+    }
+
+    fn is_clear(world: &CollisionWorld<f32, CollisionObjectData>, shape: &ShapeHandle<f32>, groups: &CollisionGroups,
+        own: &[CollisionObjectSlabHandle], position: (f32, f32)
+    ) -> bool {
+        let isometry = Isometry2::new(Vector2::new(position.0, position.1), zero());
+        for (handle, object) in world.collision_objects() {
+            let deadly = match object.data().entity_type {
+                Entity::WALL | Entity::HERBIVORE | Entity::CARNIVORE => true,
+                _ => false,
+            };
+            if !deadly || own.contains(&handle) || !groups.can_interact_with_groups(object.collision_groups()) {
+                continue;
+            }
+            if query::distance(&isometry, shape.as_ref(), object.position(), object.shape().as_ref()) <= 0.0 {
+                return false;
+            }
+        }
+        true
+    }
+}
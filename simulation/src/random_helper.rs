@@ -22,7 +22,23 @@ impl RandomHelper {
         )
     }
 
+    // Keeps a coordinate inside the area random coordinates are drawn from
+    pub fn clamp_coordinate(&self, x: f32, y: f32) -> (f32, f32) {
+        (
+            x.max(40.0).min(self.screen_size_x - 40.0),
+            y.max(40.0).min(self.screen_size_y - 40.0),
+        )
+    }
+
     pub fn random_between(&mut self, min: f32, max: f32) -> f32 {
         self.rng.gen_range(min, max)
     }
+
+    // Normally distributed value using the Box-Muller transform
+    pub fn random_gaussian(&mut self, mean: f32, sigma: f32) -> f32 {
+        let u1: f32 = self.rng.gen_range(std::f32::EPSILON, 1.0);
+        let u2: f32 = self.rng.gen_range(0.0, 1.0);
+        let standard = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f32::consts::PI * u2).cos();
+        mean + sigma * standard
+    }
 }
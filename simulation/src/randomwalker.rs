@@ -4,13 +4,22 @@ use ncollide2d::shape::ConvexPolygon;
 use ncollide2d::query::Ray;
 use ncollide2d::world::CollisionWorld;
 use ncollide2d::pipeline::object::{CollisionObjectSlabHandle, CollisionGroups};
-use nalgebra::base::Matrix;
 use nalgebra::geometry::UnitComplex;
 
 use crate::collisionobjectdata::CollisionObjectData;
-use crate::brain::{Brain, BrainNetwork};
+use crate::brain::{Brain, BrainNetwork, ACTIONS};
+use crate::fitness::Behavior;
+use crate::pareto::Objectives;
 use crate::entity::Entity;
+use crate::ledger::{Ledger, Reason};
+use crate::mutation::Mutation;
+use crate::sharing::reputation;
+use crate::species::{SpeciesConfig, MAX_SIGNAL_DIMENSIONS};
 
+// Energy is clamped to this value
+pub const MAX_ENERGY: i32 = 2500;
+// Collision group of retired agents, they stay in the world but interact with nothing
+pub const RETIRED_GROUP: usize = 8;
 
 pub struct RandomWalker {
     id: i32,
@@ -25,15 +34,33 @@ pub struct RandomWalker {
     health: i32,
     score: i32,
     entity: Entity,
+    species: i32,
     speed: f32,
     thinking_time: i32,
     facing: i8,
+    share_range: f32,
+    steps_since_meal: i32,
+    // Signal emitted in the current step and whether prey was in sight when emitting it
+    emitted_signal: Option<(Vec<f32>, bool)>,
+    // Retired agents are out of the world until the next generation
+    active: bool,
+    retired_groups: Option<(CollisionGroups, Option<CollisionGroups>)>,
+    // Id of the current genome in the lineage
+    genome_id: u64,
+    // How many times each action was chosen since the last diversity report
+    action_counts: [u64; ACTIONS],
+    // Trajectory statistics of the current life, only recorded when the novelty is used
+    behavior: Behavior,
+    novelty: f32,
+    // The value the population is sorted and selected by
+    fitness: f32,
+    objectives: Objectives,
     color: [f32; 4],
     top_color: [f32; 4],
 }
 impl RandomWalker {
-    pub fn new(handle: CollisionObjectSlabHandle, env_handle: Option<CollisionObjectSlabHandle>, id: i32, size: f32, speed: f32, health: i32, entity: Entity,
-        thinking_time: i32, view_range: f32, mutation_rate: f32, seed: u64, color: [f32; 4], top_color: [f32; 4],
+    pub fn new(handle: CollisionObjectSlabHandle, env_handle: Option<CollisionObjectSlabHandle>, id: i32, size: f32, speed: f32, health: i32, entity: Entity, species: i32,
+        thinking_time: i32, species_config: SpeciesConfig, mutation: Mutation, share_range: f32, seed: u64, color: [f32; 4], top_color: [f32; 4],
     ) -> Self {
         RandomWalker{
             id: id,
@@ -41,14 +68,26 @@ impl RandomWalker {
             handle: handle,
             env_handle: env_handle,
             facing: 1,
-            brain: Brain::new(view_range, mutation_rate, seed),
-            rays: vec![(Point2::new(1.0, 1.0), Matrix::x());8],
+            share_range: share_range,
+            steps_since_meal: 0,
+            emitted_signal: None,
+            active: true,
+            retired_groups: None,
+            genome_id: 0,
+            action_counts: [0; ACTIONS],
+            behavior: Behavior::new(),
+            novelty: 0.0,
+            fitness: 0.0,
+            objectives: Objectives::default(),
+            brain: Brain::new(species_config.sensors, species_config.communication, mutation, species_config.sharing, seed),
+            rays: Vec::new(),
             thinking: thinking_time,
             last_trans: Translation2::new(0.0, 0.0),
             initial_health: health,
             health: health,
             score: 0,
             entity: entity,
+            species: species,
             speed: speed,
             thinking_time: thinking_time,
             color: color,
@@ -64,10 +103,68 @@ impl RandomWalker {
         self.handle
     }
 
+    pub fn get_genome_id(&self) -> u64 {
+        self.genome_id
+    }
+
+    pub fn set_genome_id(&mut self, genome_id: u64) {
+        self.genome_id = genome_id;
+    }
+
+    pub fn behavior(&self) -> &Behavior {
+        &self.behavior
+    }
+
+    pub fn behavior_mut(&mut self) -> &mut Behavior {
+        &mut self.behavior
+    }
+
+    pub fn get_novelty(&self) -> f32 {
+        self.novelty
+    }
+
+    pub fn get_objectives(&self) -> &Objectives {
+        &self.objectives
+    }
+
+    pub fn get_fitness(&self) -> f32 {
+        self.fitness
+    }
+
+    pub fn set_fitness(&mut self, novelty: f32, fitness: f32) {
+        self.novelty = novelty;
+        self.fitness = fitness;
+    }
+
+    // Returns the action counts and starts counting again
+    pub fn take_action_counts(&mut self) -> [u64; ACTIONS] {
+        std::mem::replace(&mut self.action_counts, [0; ACTIONS])
+    }
+
+    pub fn get_env_handle(&self) -> Option<CollisionObjectSlabHandle> {
+        self.env_handle
+    }
+
     pub fn get_health(&self) -> i32 {
         self.health
     }
 
+    pub fn get_entity(&self) -> Entity {
+        self.entity
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    // The groups of the body while it takes part in the simulation, also while it is retired
+    pub fn get_collision_groups(&self, world: &CollisionWorld<f32, CollisionObjectData>) -> CollisionGroups {
+        match self.retired_groups {
+            Some((groups, _)) => groups,
+            None => *world.collision_object(self.handle).unwrap().collision_groups(),
+        }
+    }
+
     pub fn create_polygon(size: f32, pos_x: f32, pos_y: f32) -> ConvexPolygon<f32> {
         let points = RandomWalker::polygon_points(size, pos_x, pos_y);
         ConvexPolygon::try_new(points).expect("Convex hull computation failed.")
@@ -84,14 +181,94 @@ impl RandomWalker {
         points
     }
 
+    // Inputs of the state sub-network, every value is between -1 and 1
+    fn get_internal_state(&self, pos: Isometry2<f32>, world: &CollisionWorld<f32, CollisionObjectData>) -> Vec<f32> {
+        let energy = self.health as f32 / MAX_ENERGY as f32;
+        // Without food the agent starves in about initial_health steps
+        let hunger = (self.steps_since_meal as f32 / self.initial_health as f32).min(1.0);
+        let heading = self.facing as f32 * std::f32::consts::FRAC_PI_4;
+        let mut pack_mates = 0;
+        for (handle, object) in world.collision_objects() {
+            if handle != self.handle && object.data().entity_type == self.entity && object.data().species == self.species
+                && !object.collision_groups().is_member_of(RETIRED_GROUP)
+            {
+                let distance = (object.position().translation.vector - pos.translation.vector).norm();
+                if distance <= self.share_range {
+                    pack_mates += 1;
+                }
+            }
+        }
+        vec![
+            energy,
+            hunger,
+            heading.sin(),
+            - heading.cos(),
+            pack_mates as f32 / (pack_mates as f32 + 1.0),
+        ]
+    }
+
+    // Average signal of the agents of the heard species within the signal range
+    fn get_heard_signal(&self, pos: Isometry2<f32>, world: &CollisionWorld<f32, CollisionObjectData>) -> Vec<f32> {
+        let communication = self.brain.communication();
+        let mut heard_signal = vec![0.0; communication.dimensions];
+        let mut emitters = 0;
+        for (handle, object) in world.collision_objects() {
+            let data = object.data();
+            if handle != self.handle && data.entity_type != Entity::OTHER && communication.hears.contains(&data.species)
+                && !object.collision_groups().is_member_of(RETIRED_GROUP)
+            {
+                let distance = (object.position().translation.vector - pos.translation.vector).norm();
+                if distance <= communication.range {
+                    for (i, value) in heard_signal.iter_mut().enumerate() {
+                        *value += data.signal.get()[i];
+                    }
+                    emitters += 1;
+                }
+            }
+        }
+        if emitters > 0 {
+            for value in heard_signal.iter_mut() {
+                *value /= emitters as f32;
+            }
+        }
+        heard_signal
+    }
+
+    // The agent's own reputation and the mean reputation of its pack mates within the share range, 0.5 without pack mates
+    fn get_reputation(&self, pos: Isometry2<f32>, world: &CollisionWorld<f32, CollisionObjectData>) -> Vec<f32> {
+        let own = world.collision_object(self.handle).unwrap().data();
+        let mut total = 0.0;
+        let mut pack_mates = 0;
+        for (handle, object) in world.collision_objects() {
+            let data = object.data();
+            if handle != self.handle && data.entity_type == self.entity && data.species == self.species
+                && !object.collision_groups().is_member_of(RETIRED_GROUP)
+            {
+                let distance = (object.position().translation.vector - pos.translation.vector).norm();
+                if distance <= self.share_range {
+                    total += reputation(data.given.get(), data.received.get());
+                    pack_mates += 1;
+                }
+            }
+        }
+        let pack_reputation = if pack_mates > 0 { total / pack_mates as f32 } else { 0.5 };
+        vec![reputation(own.given.get(), own.received.get()), pack_reputation]
+    }
+
     fn get_ray_interferences<'a>(&'a mut self, new_pos: Isometry2<f32>,
         world: &'a CollisionWorld<f32, CollisionObjectData>,
-    ) -> Vec<Option<(Entity, f32)>>
+    ) -> Vec<Option<(Entity, i32, f32)>>
     {
         let mut closest_objects = Vec::new();
-        let points = self.get_sensor_points();
+        let sensor_rays = self.get_sensor_rays();
+        let ranges = self.brain.sensors().ray_ranges();
         let mut ray_group = CollisionGroups::new();
-        if self.entity == Entity::HERBIVORE {
+        if self.entity == Entity::HERBIVORE && self.brain.sensors().species_aware {
+            ray_group.set_membership(&[4]);
+            ray_group.set_whitelist(&[1, 2, 3, 5]);
+            ray_group.set_blacklist(&[4, 6, 7]);
+        }
+        else if self.entity == Entity::HERBIVORE {
             ray_group.set_membership(&[4]);
             ray_group.set_whitelist(&[1, 3, 5]);
             ray_group.set_blacklist(&[2, 4, 6, 7]);
@@ -103,15 +280,15 @@ impl RandomWalker {
         }
         self.rays = Vec::new();
         let mut was_thinking = false;
-        for i in 0..points.len() {
-            let origin = new_pos.transform_point(&points[i]);
-            let dir = Matrix::normalize(&(origin.coords - new_pos.translation.vector));
+        for i in 0..sensor_rays.len() {
+            let origin = new_pos.transform_point(&sensor_rays[i].0);
+            let dir = new_pos.transform_vector(&sensor_rays[i].1);
             self.rays.push((origin, dir));
             if self.thinking >= self.thinking_time {
                 let ray = Ray::new(origin + 0.1 * dir, dir); // 0.1 is needed for them to not detect themselves
-                let closest_object = world.first_interference_with_ray(&ray, self.brain.max_sensor_distance(), &ray_group);
+                let closest_object = world.first_interference_with_ray(&ray, ranges[i], &ray_group);
                 if let Some(obj) = closest_object {
-                    closest_objects.push(Some((obj.co.data().entity_type, obj.inter.toi)));
+                    closest_objects.push(Some((obj.co.data().entity_type, obj.co.data().species, obj.inter.toi)));
                 }
                 else {
                     closest_objects.push(None);
@@ -125,12 +302,45 @@ impl RandomWalker {
         closest_objects
     }
 
-    pub fn update(&mut self, world: &mut CollisionWorld<f32, CollisionObjectData>) {
+    pub fn update(&mut self, world: &mut CollisionWorld<f32, CollisionObjectData>, ledger: &Ledger) {
         let mut new_pos = world.collision_object(self.handle).unwrap().position().clone();
         let translation;
         let detected_objects = self.get_ray_interferences(new_pos, world);
+        self.emitted_signal = None;
         if detected_objects.len() > 0 {
-            self.facing = self.brain.get_new_direction(detected_objects, self.entity, self.facing);
+            let mut internal_state = Vec::new();
+            if self.brain.sensors().internal_state {
+                internal_state = self.get_internal_state(new_pos, world);
+            }
+            let mut heard_signal = Vec::new();
+            if self.brain.communication().hears.len() > 0 {
+                heard_signal = self.get_heard_signal(new_pos, world);
+            }
+            let mut reputation = Vec::new();
+            if self.brain.sensors().reputation {
+                reputation = self.get_reputation(new_pos, world);
+            }
+            let prey = if self.entity == Entity::HERBIVORE { Entity::FOOD } else { Entity::HERBIVORE };
+            let prey_visible = detected_objects.iter().any(|object| match object {
+                Some((entity, _, _)) => *entity == prey,
+                None => false,
+            });
+            let (facing, signal) = self.brain.get_new_direction(
+                detected_objects, self.entity, self.species, self.facing, internal_state, heard_signal, reputation
+            );
+            // Turning left adds 7 to the facing, turning right adds 1
+            let action = match (facing - self.facing + 8) % 8 {
+                7 => 0,
+                0 => 1,
+                _ => 2,
+            };
+            self.action_counts[action] += 1;
+            self.facing = facing;
+            let chase = world.collision_object(self.handle).unwrap().data().chase.get();
+            world.collision_object(self.handle).unwrap().data().chase.set(if prey_visible { chase + 1 } else { 0 });
+            if signal.len() > 0 {
+                self.emitted_signal = Some((signal, prey_visible));
+            }
             let vertical: f32;
             let horizontal: f32;
             match self.facing {
@@ -186,19 +396,36 @@ impl RandomWalker {
             }
         }
         let randomwalker_object = world.get_mut(self.handle).unwrap();
+        if let Some((signal, _)) = &self.emitted_signal {
+            let mut stored_signal = [0.0; MAX_SIGNAL_DIMENSIONS];
+            stored_signal[..signal.len()].copy_from_slice(signal);
+            randomwalker_object.data().signal.set(stored_signal);
+        }
         self.thinking += 1;
         randomwalker_object.set_position(new_pos);
+        randomwalker_object.data().sharing.set(self.brain.sharing_gene());
         self.health = randomwalker_object.data().energy.get();
-        if self.health > 2500 {
-            self.health = 2500;
-            randomwalker_object.data().energy.set(self.health);
+        if self.health > MAX_ENERGY {
+            ledger.transfer(Some(randomwalker_object.data()), None, self.health - MAX_ENERGY, Reason::ClampLoss);
+            self.health = MAX_ENERGY;
         }
         else {
-            randomwalker_object.data().energy.set(self.health - 1);
+            ledger.transfer(Some(randomwalker_object.data()), None, 1, Reason::Metabolism);
         }
         self.score += 1;
         self.score += randomwalker_object.data().fitness.get();
+        if randomwalker_object.data().fitness.get() > 0 {
+            self.steps_since_meal = 0;
+        } else {
+            self.steps_since_meal += 1;
+        }
+        self.objectives.survival += 1;
+        self.objectives.energy_gained += randomwalker_object.data().fitness.get() as i64;
+        self.objectives.energy_shared += randomwalker_object.data().shared.get() as i64;
+        self.objectives.hunts += randomwalker_object.data().hunts.get() as u64;
         randomwalker_object.data().fitness.set(0);
+        randomwalker_object.data().shared.set(0);
+        randomwalker_object.data().hunts.set(0);
         randomwalker_object.data().score.set(self.score);
     }
     pub fn draw(&self, ctx: &mut Context, world: &mut CollisionWorld<f32, CollisionObjectData>, top: bool, show_details: bool) -> GameResult<()> {
@@ -208,13 +435,7 @@ impl RandomWalker {
         let pos = randomwalker_object_pos.transform_point(&Point2::new(0.0, 0.0));
         if show_details {
             // Drawing the rays
-            let mut drawable_rays = Vec::new();
-            drawable_rays.push(&self.rays[(self.facing as usize + 6) % 8]);
-            drawable_rays.push(&self.rays[(self.facing as usize + 7) % 8]);
-            drawable_rays.push(&self.rays[(self.facing as usize) % 8]);
-            drawable_rays.push(&self.rays[(self.facing as usize + 1) % 8]);
-            drawable_rays.push(&self.rays[(self.facing as usize + 2) % 8]);
-            for (ori, dir) in drawable_rays.iter() {
+            for (ori, dir) in self.rays.iter() {
                 let draw_ori = RandomWalker::convert_point(ori.clone());
                 let draw_dir = RandomWalker::convert_point(ori.clone() + dir.clone() * 30.0);
                 let line = graphics::Mesh::new_line(
@@ -267,39 +488,151 @@ impl RandomWalker {
         eaten || out_of_energy
     }
 
-    pub fn respawn(&mut self, world: &mut CollisionWorld<f32, CollisionObjectData>, x: f32, y: f32, mutate: bool,
+    // Takes the agent out of the world: nothing can collide with it or see it until it respawns
+    pub fn retire(&mut self, world: &mut CollisionWorld<f32, CollisionObjectData>, ledger: &Ledger) {
+        let data = world.collision_object(self.handle).unwrap().data();
+        ledger.transfer(Some(data), None, data.energy.get(), Reason::Death);
+        // The dead are silent
+        data.signal.set([0.0; MAX_SIGNAL_DIMENSIONS]);
+        let mut inactive_group = CollisionGroups::new();
+        inactive_group.set_membership(&[RETIRED_GROUP]);
+        inactive_group.set_whitelist(&[]);
+        let groups = *world.collision_object(self.handle).unwrap().collision_groups();
+        world.get_mut(self.handle).unwrap().set_collision_groups(inactive_group);
+        let mut env_groups = None;
+        if let Some(env) = self.env_handle {
+            env_groups = Some(*world.collision_object(env).unwrap().collision_groups());
+            world.get_mut(env).unwrap().set_collision_groups(inactive_group);
+        }
+        self.retired_groups = Some((groups, env_groups));
+        self.active = false;
+        self.emitted_signal = None;
+    }
+
+    pub fn respawn(&mut self, world: &mut CollisionWorld<f32, CollisionObjectData>, ledger: &Ledger, x: f32, y: f32, mutate: bool,
         networks: BrainNetwork
     ) {
+        if let Some((groups, env_groups)) = self.retired_groups.take() {
+            world.get_mut(self.handle).unwrap().set_collision_groups(groups);
+            if let (Some(env), Some(env_groups)) = (self.env_handle, env_groups) {
+                world.get_mut(env).unwrap().set_collision_groups(env_groups);
+            }
+        }
+        self.active = true;
         let randomwalker_object = world.get_mut(self.handle).unwrap();
         randomwalker_object.data().eaten.set(false);
+        randomwalker_object.data().killed_by.set(None);
+        randomwalker_object.data().chase.set(0);
+        randomwalker_object.data().given.set(0);
+        randomwalker_object.data().received.set(0);
+        randomwalker_object.data().kills.set(0);
+        randomwalker_object.data().withheld.set(0);
+        randomwalker_object.data().signal.set([0.0; MAX_SIGNAL_DIMENSIONS]);
+        // The energy left from the previous life is lost
+        ledger.transfer(Some(randomwalker_object.data()), None, randomwalker_object.data().energy.get(), Reason::Death);
+        ledger.transfer(None, Some(randomwalker_object.data()), self.initial_health, Reason::Birth);
         self.health = self.initial_health;
-        randomwalker_object.data().energy.set(self.initial_health);
         randomwalker_object.set_position(Isometry2::from_parts(Translation2::new(x, y), UnitComplex::new(0.0)));
         self.brain.set_networks(networks.clone());
         if mutate {
             self.brain.mutate();
         }
+        randomwalker_object.data().sharing.set(self.brain.sharing_gene());
         self.thinking = self.thinking_time;
         self.score = 0;
+        self.behavior = Behavior::new();
+        self.novelty = 0.0;
+        self.fitness = 0.0;
+        self.objectives = Objectives::default();
+        self.steps_since_meal = 0;
+        self.emitted_signal = None;
+    }
+
+    // A new agent with the same body, senses and colors, placed on the same spot. It gets its own
+    // collision objects, and a genome when it respawns.
+    pub fn sibling(&self, world: &mut CollisionWorld<f32, CollisionObjectData>, id: i32, species_config: SpeciesConfig, seed: u64) -> Self {
+        let object = world.collision_object(self.handle).unwrap();
+        let position = *object.position();
+        let shape = object.shape().clone();
+        let query = object.query_type();
+        let groups = self.get_collision_groups(world);
+        let env_handle = match self.env_handle {
+            Some(env) => {
+                let env_object = world.collision_object(env).unwrap();
+                let env_shape = env_object.shape().clone();
+                let env_groups = match self.retired_groups {
+                    Some((_, Some(env_groups))) => env_groups,
+                    _ => *env_object.collision_groups(),
+                };
+                let env_data = CollisionObjectData::new(Entity::OTHER, id, self.species, None);
+                Some(world.add(position, env_shape, env_groups, query, env_data).0)
+            }
+            None => None,
+        };
+        let data = CollisionObjectData::new(self.entity, id, self.species, env_handle);
+        // The body holds no energy until an offspring is born into it
+        data.energy.set(0);
+        let handle = world.add(position, shape, groups, query, data).0;
+        RandomWalker::new(handle, env_handle, id, self.size, self.speed, self.initial_health, self.entity, self.species,
+            self.thinking_time, species_config, self.brain.mutation().clone(), self.share_range, seed, self.color, self.top_color,
+        )
+    }
+
+    // Gives the given share of the energy to an offspring
+    pub fn give_energy(&mut self, world: &CollisionWorld<f32, CollisionObjectData>, ledger: &Ledger, share: f32,
+        offspring: CollisionObjectSlabHandle
+    ) {
+        let data = world.collision_object(self.handle).unwrap().data();
+        let given = (data.energy.get() as f32 * share) as i32;
+        ledger.transfer(Some(data), Some(world.collision_object(offspring).unwrap().data()), given, Reason::Offspring);
+        self.health = data.energy.get();
+    }
+
+    // Returns the energy of a newborn to the environment, it lives on what its parents give it instead
+    pub fn return_energy(&mut self, world: &CollisionWorld<f32, CollisionObjectData>, ledger: &Ledger) {
+        let data = world.collision_object(self.handle).unwrap().data();
+        ledger.transfer(Some(data), None, data.energy.get(), Reason::Returned);
+        self.health = 0;
+    }
+
+    pub fn refresh_health(&mut self, world: &CollisionWorld<f32, CollisionObjectData>) {
+        self.health = world.collision_object(self.handle).unwrap().data().energy.get();
+    }
+
+    pub fn get_emitted_signal(&self) -> Option<&(Vec<f32>, bool)> {
+        self.emitted_signal.as_ref()
+    }
+
+    pub fn get_species(&self) -> i32 {
+        self.species
+    }
+
+    // Replaces the genome without respawning
+    pub fn set_brain(&mut self, networks: BrainNetwork) {
+        self.brain.set_networks(networks);
     }
 
     pub fn get_brain(&self) -> BrainNetwork {
         self.brain.get_networks()
     }
 
-    fn get_sensor_points(&self) -> Vec<Point2<f32>> {
-        let points = RandomWalker::polygon_points(self.size, 0.0, 0.0);
-        let mut sensor_points = Vec::new();
-        for i in 0..points.len() {
-            let x1 = points[i].coords.x.clone();
-            let y1 = points[i].coords.y.clone();
-            let x2 = points[(i + 1) % points.len()].coords.x.clone();
-            let y2 = points[(i + 1) % points.len()].coords.y.clone();
+    // The weights of the sub-networks in use, without copying the brain
+    pub fn brain_weights(&self) -> Vec<f32> {
+        self.brain.weights()
+    }
 
-            sensor_points.push(points[i]);
-            sensor_points.push(Point2::new((x1 + x2) / 2.0, (y1 + y2) / 2.0));
+    // Rays relative to the center of the agent, starting on the edge of the polygon
+    fn get_sensor_rays(&self) -> Vec<(Point2<f32>, Vector2<f32>)> {
+        let arm_size = self.size / 3.0;
+        let facing_angle = self.facing as f32 * std::f32::consts::FRAC_PI_4;
+        let mut sensor_rays = Vec::new();
+        for angle in self.brain.sensors().ray_angles() {
+            let dir = Vector2::new((facing_angle + angle).sin(), - (facing_angle + angle).cos());
+            // The polygon's edge satisfies |x| + |y| = arm_size
+            let distance = arm_size / (dir.x.abs() + dir.y.abs());
+            sensor_rays.push((Point2::from(dir * distance), dir));
         }
-        sensor_points
+        sensor_rays
     }
 
     // ggez and ncollide use different versions of nalgebra
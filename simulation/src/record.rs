@@ -1,6 +1,9 @@
 extern crate csv;
 use std::error::Error;
 use std::fs::File;
+use std::io;
+
+use crate::ledger::{Audit, Reason};
 
 
 #[derive(Serialize)]
@@ -31,6 +34,7 @@ impl<'a> GamestateRecord<'a> {
 #[derive(Serialize)]
 pub struct EventRecord {
     timestep: u64,
+    event_type: &'static str,
     first_id: i32,
     second_id: i32,
     first: String,
@@ -42,9 +46,10 @@ pub struct EventRecord {
 }
 
 impl EventRecord{
-    pub fn new(timestep: u64, first_id: i32, second_id: i32, first: String, second: String, pos_x: u64, pos_y: u64, first_score: u64, second_score: u64,) -> Self {
+    pub fn new(timestep: u64, event_type: &'static str, first_id: i32, second_id: i32, first: String, second: String, pos_x: u64, pos_y: u64, first_score: u64, second_score: u64,) -> Self {
         EventRecord {
             timestep: timestep,
+            event_type: event_type,
             first_id: first_id,
             second_id: second_id,
             first: first,
@@ -95,14 +100,403 @@ impl AverageRecord{
     }
 }
 
+#[derive(Serialize)]
+pub struct SignalRecord<'a> {
+    timestep: u64,
+    id: i32,
+    species: &'a str,
+    x: f32,
+    y: f32,
+    health: i32,
+    prey_visible: bool,
+    signal_1: f32,
+    signal_2: f32,
+}
+
+impl<'a> SignalRecord<'a> {
+    pub fn new(timestep: u64, id: i32, species: &'a str, x: f32, y: f32, health: i32, prey_visible: bool, signal_1: f32, signal_2: f32) -> Self {
+        SignalRecord {
+            timestep: timestep,
+            id: id,
+            species: species,
+            x: x,
+            y: y,
+            health: health,
+            prey_visible: prey_visible,
+            signal_1: signal_1,
+            signal_2: signal_2,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct GenerationRecord<'a> {
+    generation: u64,
+    timestep: u64,
+    species: &'a str,
+    avg_score: f32,
+    max_score: i32,
+    min_score: i32,
+    survivors: u64,
+}
+
+impl<'a> GenerationRecord<'a> {
+    pub fn new(generation: u64, timestep: u64, species: &'a str, avg_score: f32, max_score: i32, min_score: i32, survivors: u64) -> Self {
+        GenerationRecord {
+            generation: generation,
+            timestep: timestep,
+            species: species,
+            avg_score: avg_score,
+            max_score: max_score,
+            min_score: min_score,
+            survivors: survivors,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct GenomeRecord<'a> {
+    genome_id: u64,
+    species: &'a str,
+    agent_id: i32,
+    parents: String,
+    birth: u64,
+    death: Option<u64>,
+    cause: String,
+}
+
+impl<'a> GenomeRecord<'a> {
+    pub fn new(genome_id: u64, species: &'a str, agent_id: i32, parents: String, birth: u64, death: Option<u64>, cause: String) -> Self {
+        GenomeRecord {
+            genome_id: genome_id,
+            species: species,
+            agent_id: agent_id,
+            parents: parents,
+            birth: birth,
+            death: death,
+            cause: cause,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct PhylogenyEdgeRecord<'a> {
+    parent_id: u64,
+    child_id: u64,
+    parent_rank: usize,
+    species: &'a str,
+    timestep: u64,
+}
+
+impl<'a> PhylogenyEdgeRecord<'a> {
+    pub fn new(parent_id: u64, child_id: u64, parent_rank: usize, species: &'a str, timestep: u64) -> Self {
+        PhylogenyEdgeRecord {
+            parent_id: parent_id,
+            child_id: child_id,
+            parent_rank: parent_rank,
+            species: species,
+            timestep: timestep,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct DiversityRecord<'a> {
+    timestep: u64,
+    species: &'a str,
+    population: u64,
+    mean_distance: f32,
+    clusters: u64,
+    left: f32,
+    straight: f32,
+    right: f32,
+    action_entropy: f32,
+}
+
+impl<'a> DiversityRecord<'a> {
+    pub fn new(timestep: u64, species: &'a str, population: u64, mean_distance: f32, clusters: u64, left: f32, straight: f32, right: f32, action_entropy: f32) -> Self {
+        DiversityRecord {
+            timestep: timestep,
+            species: species,
+            population: population,
+            mean_distance: mean_distance,
+            clusters: clusters,
+            left: left,
+            straight: straight,
+            right: right,
+            action_entropy: action_entropy,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct MigrationRecord<'a> {
+    timestep: u64,
+    species: &'a str,
+    source_island: u64,
+    target_island: u64,
+    source_genome_id: u64,
+    target_genome_id: u64,
+}
+
+impl<'a> MigrationRecord<'a> {
+    pub fn new(timestep: u64, species: &'a str, source_island: u64, target_island: u64, source_genome_id: u64, target_genome_id: u64) -> Self {
+        MigrationRecord {
+            timestep: timestep,
+            species: species,
+            source_island: source_island,
+            target_island: target_island,
+            source_genome_id: source_genome_id,
+            target_genome_id: target_genome_id,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ParetoRecord<'a> {
+    timestep: u64,
+    species: &'a str,
+    genome_id: u64,
+    survival: u64,
+    energy_gained: i64,
+    energy_shared: i64,
+    hunts: u64,
+}
+
+impl<'a> ParetoRecord<'a> {
+    pub fn new(timestep: u64, species: &'a str, genome_id: u64, survival: u64, energy_gained: i64, energy_shared: i64, hunts: u64) -> Self {
+        ParetoRecord {
+            timestep: timestep,
+            species: species,
+            genome_id: genome_id,
+            survival: survival,
+            energy_gained: energy_gained,
+            energy_shared: energy_shared,
+            hunts: hunts,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct PopulationRecord<'a> {
+    timestep: u64,
+    species: &'a str,
+    population: u64,
+    births: u64,
+    deaths: u64,
+}
+
+impl<'a> PopulationRecord<'a> {
+    pub fn new(timestep: u64, species: &'a str, population: u64, births: u64, deaths: u64) -> Self {
+        PopulationRecord {
+            timestep: timestep,
+            species: species,
+            population: population,
+            births: births,
+            deaths: deaths,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct SharingRecord<'a> {
+    timestep: u64,
+    species: &'a str,
+    population: u64,
+    mean: f32,
+    std: f32,
+    min: f32,
+    median: f32,
+    max: f32,
+}
+
+impl<'a> SharingRecord<'a> {
+    pub fn new(timestep: u64, species: &'a str, population: u64, mean: f32, std: f32, min: f32, median: f32, max: f32) -> Self {
+        SharingRecord {
+            timestep: timestep,
+            species: species,
+            population: population,
+            mean: mean,
+            std: std,
+            min: min,
+            median: median,
+            max: max,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ReputationRecord<'a> {
+    timestep: u64,
+    species: &'a str,
+    population: u64,
+    mean_given: f32,
+    mean_received: f32,
+    mean_reputation: f32,
+    free_riders: u64,
+    free_rider_energy: i64,
+    withheld: i64,
+}
+
+impl<'a> ReputationRecord<'a> {
+    pub fn new(timestep: u64, species: &'a str, population: u64, mean_given: f32, mean_received: f32, mean_reputation: f32,
+        free_riders: u64, free_rider_energy: i64, withheld: i64
+    ) -> Self {
+        ReputationRecord {
+            timestep: timestep,
+            species: species,
+            population: population,
+            mean_given: mean_given,
+            mean_received: mean_received,
+            mean_reputation: mean_reputation,
+            free_riders: free_riders,
+            free_rider_energy: free_rider_energy,
+            withheld: withheld,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct HuntRecord<'a> {
+    hunt_id: u64,
+    timestep: u64,
+    hunter_id: i32,
+    hunter_species: &'a str,
+    prey_id: i32,
+    prey_score: i32,
+    x: f32,
+    y: f32,
+    pack: u64,
+    nutrition: i32,
+    kept: i32,
+    lost: i32,
+    recipients: u64,
+    mean_relatedness: f32,
+}
+
+impl<'a> HuntRecord<'a> {
+    pub fn new(hunt_id: u64, timestep: u64, hunter_id: i32, hunter_species: &'a str, prey_id: i32, prey_score: i32, x: f32, y: f32,
+        pack: u64, nutrition: i32, kept: i32, lost: i32, recipients: u64, mean_relatedness: f32
+    ) -> Self {
+        HuntRecord {
+            hunt_id: hunt_id,
+            timestep: timestep,
+            hunter_id: hunter_id,
+            hunter_species: hunter_species,
+            prey_id: prey_id,
+            prey_score: prey_score,
+            x: x,
+            y: y,
+            pack: pack,
+            nutrition: nutrition,
+            kept: kept,
+            lost: lost,
+            recipients: recipients,
+            mean_relatedness: mean_relatedness,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct HuntRecipientRecord<'a> {
+    hunt_id: u64,
+    species: &'a str,
+    id: i32,
+    distance: f32,
+    energy: i32,
+    relatedness: f32,
+}
+
+impl<'a> HuntRecipientRecord<'a> {
+    pub fn new(hunt_id: u64, species: &'a str, id: i32, distance: f32, energy: i32, relatedness: f32) -> Self {
+        HuntRecipientRecord {
+            hunt_id: hunt_id,
+            species: species,
+            id: id,
+            distance: distance,
+            energy: energy,
+            relatedness: relatedness,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct LedgerRecord<'a> {
+    timestep: u64,
+    source: String,
+    sink: String,
+    amount: i32,
+    reason: &'a str,
+}
+
+impl<'a> LedgerRecord<'a> {
+    pub fn new(timestep: u64, source: String, sink: String, amount: i32, reason: &'a str) -> Self {
+        LedgerRecord {
+            timestep: timestep,
+            source: source,
+            sink: sink,
+            amount: amount,
+            reason: reason,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct EnergyRecord {
+    timestep: u64,
+    total: i64,
+    expected: i64,
+    discrepancy: i64,
+    birth: i64,
+    metabolism: i64,
+    food: i64,
+    hunt: i64,
+    share: i64,
+    offspring: i64,
+    clamp_loss: i64,
+    death: i64,
+    lost: i64,
+    returned: i64,
+    rounding_loss: f32,
+}
+
+impl EnergyRecord {
+    pub fn new(timestep: u64, audit: &Audit) -> Self {
+        EnergyRecord {
+            timestep: timestep,
+            total: audit.total,
+            expected: audit.expected,
+            discrepancy: audit.discrepancy(),
+            birth: audit.moved(Reason::Birth),
+            metabolism: audit.moved(Reason::Metabolism),
+            food: audit.moved(Reason::Food),
+            hunt: audit.moved(Reason::Hunt),
+            share: audit.moved(Reason::Share),
+            offspring: audit.moved(Reason::Offspring),
+            clamp_loss: audit.moved(Reason::ClampLoss),
+            death: audit.moved(Reason::Death),
+            lost: audit.moved(Reason::Lost),
+            returned: audit.moved(Reason::Returned),
+            rounding_loss: audit.rounding_loss,
+        }
+    }
+}
+
 pub struct Writer {
-    writer: csv::Writer<File>,
+    writer: csv::Writer<Box<dyn io::Write>>,
 }
 
 impl<'a> Writer {
     pub fn new(filename: &'a str) -> Self {
         Writer {
-            writer: csv::Writer::from_path(filename).unwrap(),
+            writer: csv::Writer::from_writer(Box::new(File::create(filename).unwrap())),
+        }
+    }
+
+    // Discards every record, used by the headless evaluations
+    pub fn sink() -> Self {
+        Writer {
+            writer: csv::Writer::from_writer(Box::new(io::sink())),
         }
     }
 
@@ -124,4 +518,99 @@ impl<'a> Writer {
         Ok(())
     }
 
+    pub fn write_signal_record(&mut self, record: SignalRecord) -> Result<(), Box<dyn Error>> {
+        self.writer.serialize(&record)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    pub fn write_generation_record(&mut self, record: GenerationRecord) -> Result<(), Box<dyn Error>> {
+        self.writer.serialize(&record)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    pub fn write_genome_record(&mut self, record: GenomeRecord) -> Result<(), Box<dyn Error>> {
+        self.writer.serialize(&record)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    pub fn write_phylogeny_edge_record(&mut self, record: PhylogenyEdgeRecord) -> Result<(), Box<dyn Error>> {
+        self.writer.serialize(&record)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    pub fn write_diversity_record(&mut self, record: DiversityRecord) -> Result<(), Box<dyn Error>> {
+        self.writer.serialize(&record)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    pub fn write_migration_record(&mut self, record: MigrationRecord) -> Result<(), Box<dyn Error>> {
+        self.writer.serialize(&record)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    pub fn write_pareto_record(&mut self, record: ParetoRecord) -> Result<(), Box<dyn Error>> {
+        self.writer.serialize(&record)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    pub fn write_population_record(&mut self, record: PopulationRecord) -> Result<(), Box<dyn Error>> {
+        self.writer.serialize(&record)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    pub fn write_sharing_record(&mut self, record: SharingRecord) -> Result<(), Box<dyn Error>> {
+        self.writer.serialize(&record)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    pub fn write_reputation_record(&mut self, record: ReputationRecord) -> Result<(), Box<dyn Error>> {
+        self.writer.serialize(&record)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    pub fn write_hunt_record(&mut self, record: HuntRecord) -> Result<(), Box<dyn Error>> {
+        self.writer.serialize(&record)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    pub fn write_hunt_recipient_record(&mut self, record: HuntRecipientRecord) -> Result<(), Box<dyn Error>> {
+        self.writer.serialize(&record)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    pub fn write_ledger_record(&mut self, record: LedgerRecord) -> Result<(), Box<dyn Error>> {
+        self.writer.serialize(&record)?;
+        Ok(())
+    }
+
+    pub fn write_energy_record(&mut self, record: EnergyRecord) -> Result<(), Box<dyn Error>> {
+        self.writer.serialize(&record)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    // A row of a table whose columns are only known at runtime
+    pub fn write_row(&mut self, row: &[String]) -> Result<(), Box<dyn Error>> {
+        self.writer.write_record(row)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
 }
\ No newline at end of file
@@ -0,0 +1,129 @@
+use serde_json::Value;
+
+use crate::random_helper::RandomHelper;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SelectionStrategy {
+    // The best of tournament_size uniformly drawn agents
+    Tournament,
+    // Probability proportional to the fitness, shifted so that the worst agent has zero weight
+    FitnessProportional,
+    // Probability proportional to the rank, the best agent has the highest weight
+    Rank,
+    // Uniformly among the top truncation_fraction of the population
+    Truncation,
+}
+
+impl SelectionStrategy {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "tournament" => SelectionStrategy::Tournament,
+            "fitness_proportional" => SelectionStrategy::FitnessProportional,
+            "rank" => SelectionStrategy::Rank,
+            "truncation" => SelectionStrategy::Truncation,
+            _ => panic!("Unknown selection strategy: {}", name),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ElitismPolicy {
+    // Elites keep their genome: when they die they respawn unmutated
+    Keep,
+    // Elites are replaced by offspring like every other agent
+    None,
+}
+
+impl ElitismPolicy {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "keep" => ElitismPolicy::Keep,
+            "none" => ElitismPolicy::None,
+            _ => panic!("Unknown elitism policy: {}", name),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Selection {
+    pub strategy: SelectionStrategy,
+    pub tournament_size: usize,
+    pub truncation_fraction: f32,
+    pub elite_fraction: f32,
+    pub elitism: ElitismPolicy,
+}
+
+impl Selection {
+    pub fn from_config(config: &Value) -> Self {
+        Selection {
+            strategy: SelectionStrategy::from_name(config["strategy"].as_str().unwrap()),
+            tournament_size: config["tournament_size"].as_u64().unwrap() as usize,
+            truncation_fraction: config["truncation_fraction"].as_f64().unwrap() as f32,
+            elite_fraction: config["elite_fraction"].as_f64().unwrap() as f32,
+            elitism: ElitismPolicy::from_name(config["elitism"].as_str().unwrap()),
+        }
+    }
+
+    // Number of top agents counted as elites, at least one unless the fraction is zero
+    pub fn elite_count(&self, population: usize) -> usize {
+        if population == 0 || self.elite_fraction <= 0.0 {
+            return 0;
+        }
+        ((population as f32 * self.elite_fraction).round() as usize).max(1).min(population)
+    }
+
+    // Returns the index of the selected parent, None for an empty population. The fitness values have
+    // to be sorted in descending order.
+    pub fn select(&self, fitness: &[f32], rand: &mut RandomHelper) -> Option<usize> {
+        let population = fitness.len();
+        match self.strategy {
+            SelectionStrategy::Tournament => {
+                let mut winner = Selection::random_index(population, rand)?;
+                for _ in 1..self.tournament_size {
+                    let contender = Selection::random_index(population, rand)?;
+                    if fitness[contender] > fitness[winner] {
+                        winner = contender;
+                    }
+                }
+                Some(winner)
+            }
+            SelectionStrategy::FitnessProportional => {
+                let worst = *fitness.last()?;
+                let weights: Vec<f32> = fitness.iter().map(|value| value - worst).collect();
+                Selection::roulette(&weights, rand)
+            }
+            SelectionStrategy::Rank => {
+                let weights: Vec<f32> = (0..population).map(|rank| (population - rank) as f32).collect();
+                Selection::roulette(&weights, rand)
+            }
+            SelectionStrategy::Truncation => {
+                let count = ((population as f32 * self.truncation_fraction).round() as usize).max(1).min(population);
+                Selection::random_index(count, rand)
+            }
+        }
+    }
+
+    // None when there is nothing to choose from
+    fn random_index(count: usize, rand: &mut RandomHelper) -> Option<usize> {
+        if count == 0 {
+            return None;
+        }
+        Some((rand.random_between(0.0, count as f32) as usize).min(count - 1))
+    }
+
+    // Falls back to a uniform choice when every weight is zero
+    fn roulette(weights: &[f32], rand: &mut RandomHelper) -> Option<usize> {
+        let total: f32 = weights.iter().sum();
+        if total <= 0.0 {
+            return Selection::random_index(weights.len(), rand);
+        }
+        let mut target = rand.random_between(0.0, total);
+        for (i, weight) in weights.iter().enumerate() {
+            if target < *weight {
+                return Some(i);
+            }
+            target -= weight;
+        }
+        Some(weights.len() - 1)
+    }
+}
@@ -0,0 +1,344 @@
+use serde_json::{json, Value};
+
+use crate::species::species_id;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SharingPolicy {
+    // Every carnivore within the share range gets the same amount, the hunter counts as a recipient but its part is lost
+    Equal,
+    // Every carnivore within the share range gets the same amount, the hunter keeps what is left
+    EqualKeep,
+    // The amount falls linearly with the distance from the hunter, to zero at the edge of the share range
+    Distance,
+    // Proportional to how long each carnivore has been chasing prey
+    Contribution,
+    // Only the k carnivores nearest to the hunter, in equal parts
+    Nearest,
+    // Only the carnivores of the hunter's own species, in equal parts
+    Kin,
+    // Proportional to the relatedness to the hunter
+    Relatedness,
+}
+
+impl SharingPolicy {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "equal" => SharingPolicy::Equal,
+            "equal_keep" => SharingPolicy::EqualKeep,
+            "distance" => SharingPolicy::Distance,
+            "contribution" => SharingPolicy::Contribution,
+            "nearest" => SharingPolicy::Nearest,
+            "kin" => SharingPolicy::Kin,
+            "relatedness" => SharingPolicy::Relatedness,
+            _ => panic!("Unknown sharing policy: {}", name),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RelatednessMeasure {
+    // From the lineage: 0.5 for every birth separating two genomes through their most recent common ancestor
+    Genealogical,
+    // From the genomes: exp(-distance / scale) with the Euclidean distance of the weights
+    Genetic,
+}
+
+impl RelatednessMeasure {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "genealogical" => RelatednessMeasure::Genealogical,
+            "genetic" => RelatednessMeasure::Genetic,
+            _ => panic!("Unknown relatedness measure: {}", name),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SharingRecipients {
+    // Only the carnivores of the hunter's own species
+    SameSpecies,
+    // Every carnivore, whatever its species
+    AllCarnivores,
+    // The carnivores of the species listed as allies of the hunter's species
+    Alliance,
+}
+
+impl SharingRecipients {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "same_species" => SharingRecipients::SameSpecies,
+            "all_carnivores" => SharingRecipients::AllCarnivores,
+            "alliance" => SharingRecipients::Alliance,
+            _ => panic!("Unknown sharing recipients: {}", name),
+        }
+    }
+}
+
+// A carnivore within the hunter's share range when the prey is eaten
+pub struct Recipient {
+    pub distance: f32,
+    pub same_species: bool,
+    // Decisions in a row in which the carnivore saw a herbivore
+    pub chase: i32,
+    // Between 0 and 1, 1 for the hunter's own genome
+    pub relatedness: f32,
+}
+
+// A part of a prey given to a carnivore of the pack
+pub struct Share {
+    pub species: i32,
+    pub id: i32,
+    pub distance: f32,
+    pub energy: i32,
+    pub relatedness: f32,
+}
+
+// A caught herbivore: the pack that caught it, its nutrition and how it was divided
+pub struct Hunt {
+    pub pack: usize,
+    pub nutrition: i32,
+    // What the hunter ate itself and what nobody got
+    pub kept: i32,
+    pub lost: i32,
+    pub shares: Vec<Share>,
+}
+
+#[derive(Clone)]
+pub struct Sharing {
+    pub policy: SharingPolicy,
+    // Number of recipients of the nearest policy
+    pub k: usize,
+    pub recipients: SharingRecipients,
+    // Species the hunter shares with in the alliance mode, the own species only if it is listed
+    pub allies: Vec<i32>,
+    pub relatedness: RelatednessMeasure,
+    // Generations searched for a common ancestor by the genealogical measure
+    pub relatedness_depth: usize,
+    // Genome distance at which the genetic relatedness falls to 1/e
+    pub relatedness_scale: f32,
+    // The shared fraction is a gene of every agent instead of the group's sharing percentage
+    pub evolvable: bool,
+    // Bounds of the gene and the standard deviation of its mutation
+    pub gene_min: f32,
+    pub gene_max: f32,
+    pub gene_sigma: f32,
+    // Gene of the initial agents, the group's sharing percentage
+    pub initial_gene: f32,
+    // Carnivores that gave less than this fraction of what they received get nothing, 0 turns it off
+    pub reputation_threshold: f32,
+    // Energy a carnivore has to have received before its reputation counts
+    pub reputation_grace: i32,
+}
+
+impl Sharing {
+    // Without a sharing block the prey is divided equally among the carnivores of the hunter's species
+    pub fn from_config(config: &Value, species: &Value, name: &str) -> Self {
+        if species.is_null() {
+            return Sharing::from_config(config, &json!({
+                "policy": "equal",
+                "k": 3,
+                "recipients": "same_species",
+                "allies": [],
+                "relatedness": "genealogical",
+                "relatedness_depth": 8,
+                "relatedness_scale": 5.0,
+                "evolvable": false,
+                "gene_min": 0.0,
+                "gene_max": 1.0,
+                "gene_sigma": 0.05,
+                "reputation_threshold": 0.0,
+                "reputation_grace": 500
+            }), name);
+        }
+        let percentage = match name {
+            "carnivore_1" => config["sharing_percentage_1"].as_i64().unwrap(),
+            "carnivore_2" => config["sharing_percentage_2"].as_i64().unwrap(),
+            _ => 0,
+        };
+        let mut sharing = Sharing {
+            policy: SharingPolicy::from_name(species["policy"].as_str().unwrap()),
+            k: species["k"].as_u64().unwrap() as usize,
+            recipients: SharingRecipients::from_name(species["recipients"].as_str().unwrap()),
+            allies: species["allies"].as_array().unwrap().iter().map(|name| species_id(name.as_str().unwrap())).collect(),
+            relatedness: RelatednessMeasure::from_name(species["relatedness"].as_str().unwrap()),
+            relatedness_depth: species["relatedness_depth"].as_u64().unwrap() as usize,
+            relatedness_scale: species["relatedness_scale"].as_f64().unwrap() as f32,
+            evolvable: species["evolvable"].as_bool().unwrap(),
+            gene_min: species["gene_min"].as_f64().unwrap() as f32,
+            gene_max: species["gene_max"].as_f64().unwrap() as f32,
+            gene_sigma: species["gene_sigma"].as_f64().unwrap() as f32,
+            initial_gene: percentage as f32 / 100.0,
+            reputation_threshold: species["reputation_threshold"].as_f64().unwrap() as f32,
+            reputation_grace: species["reputation_grace"].as_i64().unwrap() as i32,
+        };
+        // The initial agents' gene has to lie within the bounds like every mutated one
+        sharing.initial_gene = sharing.clamp_gene(sharing.initial_gene);
+        sharing
+    }
+
+    // Whether a carnivore of the recipient species can get a share from a hunter of the hunter species
+    pub fn receives(&self, hunter_species: i32, recipient_species: i32) -> bool {
+        match self.recipients {
+            SharingRecipients::SameSpecies => recipient_species == hunter_species,
+            SharingRecipients::AllCarnivores => true,
+            SharingRecipients::Alliance => self.allies.contains(&recipient_species),
+        }
+    }
+
+    // Whether the hunter keeps the share of a recipient with a poor give/receive ratio
+    pub fn withholds(&self, given: i32, received: i32) -> bool {
+        self.reputation_threshold > 0.0 && received > 0 && received >= self.reputation_grace
+            && (given as f32) < self.reputation_threshold * received as f32
+    }
+
+    pub fn clamp_gene(&self, gene: f32) -> f32 {
+        gene.max(self.gene_min).min(self.gene_max)
+    }
+
+    // Share of the sharable energy every recipient gets, all zero when nobody gets anything
+    pub fn weights(&self, recipients: &[Recipient], share_range: f32) -> Vec<f32> {
+        let mut weights: Vec<f32> = match self.policy {
+            SharingPolicy::Equal | SharingPolicy::EqualKeep => vec![1.0; recipients.len()],
+            SharingPolicy::Distance => recipients.iter()
+                .map(|recipient| (1.0 - recipient.distance / share_range).max(0.0))
+                .collect(),
+            SharingPolicy::Contribution => recipients.iter().map(|recipient| recipient.chase.max(0) as f32).collect(),
+            SharingPolicy::Nearest => {
+                let mut order: Vec<usize> = (0..recipients.len()).collect();
+                order.sort_by(|&a, &b| recipients[a].distance.partial_cmp(&recipients[b].distance).unwrap());
+                let mut weights = vec![0.0; recipients.len()];
+                for &i in order.iter().take(self.k) {
+                    weights[i] = 1.0;
+                }
+                weights
+            }
+            SharingPolicy::Kin => recipients.iter().map(|recipient| if recipient.same_species { 1.0 } else { 0.0 }).collect(),
+            SharingPolicy::Relatedness => recipients.iter().map(|recipient| recipient.relatedness.max(0.0)).collect(),
+        };
+        let sum: f32 = weights.iter().sum();
+        if sum > 0.0 {
+            for weight in weights.iter_mut() {
+                *weight /= sum;
+            }
+        }
+        weights
+    }
+
+    // Whole energy units the hunter keeps out of a prey and the recipients that get a share, as their
+    // index and part, the rest is lost. The equal policy divides like the original simulation: the
+    // shared fraction is split among the recipients and the hunter, whose part is lost, and every part
+    // is rounded down on its own. The other policies round the nutrition down once, the recipients with
+    // a positive weight get a share and the hunter keeps what they do not get.
+    pub fn divide(&self, nutrition: f32, fraction: f32, recipients: &[Recipient], share_range: f32) -> (i32, Vec<(usize, i32)>) {
+        if self.policy == SharingPolicy::Equal {
+            let share = (nutrition * fraction / (recipients.len() + 1) as f32) as i32;
+            return ((nutrition * (1.0 - fraction)) as i32, (0..recipients.len()).map(|i| (i, share)).collect());
+        }
+        let whole = nutrition as i32;
+        let sharable = (whole as f32 * fraction) as i32;
+        let parts: Vec<(usize, i32)> = self.weights(recipients, share_range).iter()
+            .enumerate()
+            .filter(|(_, &weight)| weight > 0.0)
+            .map(|(i, weight)| (i, (sharable as f32 * weight) as i32))
+            .collect();
+        (whole - parts.iter().map(|(_, part)| part).sum::<i32>(), parts)
+    }
+}
+
+// The give/receive ratio mapped to [0, 1]: given / (given + received), 0.5 for an agent that did neither
+pub fn reputation(given: i32, received: i32) -> f32 {
+    let total = given.max(0) + received.max(0);
+    if total == 0 {
+        0.5
+    } else {
+        given.max(0) as f32 / total as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sharing(policy: SharingPolicy) -> Sharing {
+        Sharing { policy: policy, ..Sharing::from_config(&json!({}), &Value::Null, "herbivore") }
+    }
+
+    fn recipient(distance: f32, same_species: bool, chase: i32, relatedness: f32) -> Recipient {
+        Recipient {
+            distance: distance,
+            same_species: same_species,
+            chase: chase,
+            relatedness: relatedness,
+        }
+    }
+
+    fn recipients() -> Vec<Recipient> {
+        vec![recipient(10.0, true, 3, 0.5), recipient(30.0, false, 1, 0.0), recipient(20.0, true, 0, 0.25)]
+    }
+
+    fn assert_weights(policy: SharingPolicy, expected: &[f32]) {
+        let weights = sharing(policy).weights(&recipients(), 40.0);
+        assert_eq!(weights.len(), expected.len());
+        for (weight, expected) in weights.iter().zip(expected.iter()) {
+            assert!((weight - expected).abs() < 1e-6, "{:?}: {:?}", policy, weights);
+        }
+    }
+
+    #[test]
+    fn weights_of_every_policy() {
+        assert_weights(SharingPolicy::Equal, &[1.0 / 3.0; 3]);
+        assert_weights(SharingPolicy::EqualKeep, &[1.0 / 3.0; 3]);
+        assert_weights(SharingPolicy::Distance, &[0.5, 1.0 / 6.0, 1.0 / 3.0]);
+        assert_weights(SharingPolicy::Contribution, &[0.75, 0.25, 0.0]);
+        assert_weights(SharingPolicy::Kin, &[0.5, 0.0, 0.5]);
+        assert_weights(SharingPolicy::Relatedness, &[2.0 / 3.0, 0.0, 1.0 / 3.0]);
+    }
+
+    #[test]
+    fn nearest_shares_with_the_k_nearest() {
+        let nearest = Sharing { k: 2, ..sharing(SharingPolicy::Nearest) };
+        assert_eq!(nearest.weights(&recipients(), 40.0), vec![0.5, 0.0, 0.5]);
+    }
+
+    #[test]
+    fn weights_are_zero_when_nobody_gets_anything() {
+        let unrelated = vec![recipient(10.0, false, 0, 0.0)];
+        assert_eq!(sharing(SharingPolicy::Relatedness).weights(&unrelated, 40.0), vec![0.0]);
+        assert!(sharing(SharingPolicy::Equal).weights(&[], 40.0).is_empty());
+    }
+
+    #[test]
+    fn equal_divides_like_the_original_simulation() {
+        // 100 * 0.5 / (2 + 1) for every recipient, the hunter's part is lost
+        let (kept, parts) = sharing(SharingPolicy::Equal).divide(100.0, 0.5, &recipients()[..2], 40.0);
+        assert_eq!(kept, 50);
+        assert_eq!(parts, vec![(0, 16), (1, 16)]);
+    }
+
+    #[test]
+    fn other_policies_keep_the_rest() {
+        let (kept, parts) = sharing(SharingPolicy::EqualKeep).divide(100.7, 0.5, &recipients(), 40.0);
+        assert_eq!(parts, vec![(0, 16), (1, 16), (2, 16)]);
+        assert_eq!(kept, 52);
+        let (kept, parts) = sharing(SharingPolicy::Kin).divide(100.0, 0.5, &recipients(), 40.0);
+        assert_eq!(parts, vec![(0, 25), (2, 25)]);
+        assert_eq!(kept, 50);
+    }
+
+    #[test]
+    fn withholds_from_poor_givers_after_the_grace() {
+        let sharing = Sharing { reputation_threshold: 0.5, reputation_grace: 100, ..sharing(SharingPolicy::Equal) };
+        assert!(sharing.withholds(40, 100));
+        assert!(!sharing.withholds(50, 100));
+        assert!(!sharing.withholds(0, 99));
+        let off = Sharing { reputation_threshold: 0.0, ..sharing };
+        assert!(!off.withholds(0, 1000));
+    }
+
+    #[test]
+    fn reputation_is_the_given_fraction() {
+        assert_eq!(reputation(0, 0), 0.5);
+        assert_eq!(reputation(30, 10), 0.75);
+        assert_eq!(reputation(-5, 10), 0.0);
+    }
+}
@@ -0,0 +1,198 @@
+use serde_json::Value;
+
+use crate::capture::Capture;
+use crate::fitness::Fitness;
+use crate::placement::Placement;
+use crate::selection::Selection;
+use crate::sharing::Sharing;
+
+// Species ids carried by the collision data, food and walls belong to no species
+pub const NO_SPECIES: i32 = -1;
+pub const HERBIVORE_SPECIES: i32 = 0;
+pub const CARNIVORE_SPECIES_1: i32 = 1;
+pub const CARNIVORE_SPECIES_2: i32 = 2;
+
+// Names of the species in the parameters, indexed by the species id
+pub const SPECIES_NAMES: [&str; 3] = ["herbivore", "carnivore_1", "carnivore_2"];
+
+// Signals are stored in fixed size arrays, this many dimensions can be used at most
+pub const MAX_SIGNAL_DIMENSIONS: usize = 2;
+
+pub fn species_id(name: &str) -> i32 {
+    match SPECIES_NAMES.iter().position(|species_name| *species_name == name) {
+        Some(id) => id as i32,
+        None => panic!("Unknown species: {}", name),
+    }
+}
+
+#[derive(Clone)]
+pub struct SensorConfig {
+    pub ray_count: usize,
+    // Angle covered by the front rays in degrees, centered on the facing direction
+    pub field_of_view: f32,
+    // Range of every front ray, the rear rays use the range of the front ray they mirror
+    pub ranges: Vec<f32>,
+    // Mirrors the front rays to the back of the agent
+    pub rear_rays: bool,
+    // Agents of the own species are reported on a separate channel
+    pub species_aware: bool,
+    // Energy, hunger, heading and nearby pack mates are fed to their own sub-network
+    pub internal_state: bool,
+    // The own reputation and the mean reputation of the pack mates are fed to their own sub-network
+    pub reputation: bool,
+}
+
+impl SensorConfig {
+    pub fn from_config(config: &Value) -> Self {
+        let ray_count = config["ray_count"].as_u64().unwrap() as usize;
+        let ranges = match config["range"].as_array() {
+            Some(ranges) => ranges.iter().map(|range| range.as_f64().unwrap() as f32).collect(),
+            None => vec![config["range"].as_f64().unwrap() as f32; ray_count],
+        };
+        assert_eq!(ranges.len(), ray_count, "Every ray needs its own range");
+        SensorConfig {
+            ray_count: ray_count,
+            field_of_view: config["field_of_view"].as_f64().unwrap() as f32,
+            ranges: ranges,
+            rear_rays: config["rear_rays"].as_bool().unwrap(),
+            species_aware: config["species_aware"].as_bool().unwrap(),
+            internal_state: config["internal_state"].as_bool().unwrap(),
+            reputation: config["reputation"].as_bool().unwrap(),
+        }
+    }
+
+    // Ray directions in radians relative to the facing direction
+    pub fn ray_angles(&self) -> Vec<f32> {
+        let field_of_view = self.field_of_view.to_radians();
+        let mut angles = Vec::new();
+        if self.ray_count == 1 {
+            angles.push(0.0);
+        } else if self.field_of_view >= 360.0 {
+            for i in 0..self.ray_count {
+                angles.push(- field_of_view / 2.0 + i as f32 * field_of_view / self.ray_count as f32);
+            }
+        } else {
+            for i in 0..self.ray_count {
+                angles.push(- field_of_view / 2.0 + i as f32 * field_of_view / (self.ray_count - 1) as f32);
+            }
+        }
+        if self.rear_rays {
+            for i in 0..self.ray_count {
+                angles.push(angles[i] + std::f32::consts::PI);
+            }
+        }
+        angles
+    }
+
+    pub fn ray_ranges(&self) -> Vec<f32> {
+        let mut ranges = self.ranges.clone();
+        if self.rear_rays {
+            ranges.extend_from_slice(&self.ranges);
+        }
+        ranges
+    }
+
+    // Number of rays, which is also the input size of every sub-network
+    pub fn total_rays(&self) -> usize {
+        if self.rear_rays {
+            self.ray_count * 2
+        } else {
+            self.ray_count
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct CommunicationConfig {
+    // Length of the signal vector, shared by every species
+    pub dimensions: usize,
+    // Distance from which a signal can be heard
+    pub range: f32,
+    // The brain has a signal output
+    pub emit: bool,
+    // Species whose signals are received as an input
+    pub hears: Vec<i32>,
+}
+
+impl CommunicationConfig {
+    pub fn from_config(config: &Value, species: &Value) -> Self {
+        let dimensions = config["signal_dimensions"].as_u64().unwrap() as usize;
+        assert!(dimensions >= 1 && dimensions <= MAX_SIGNAL_DIMENSIONS, "Signals can have 1 or 2 dimensions");
+        CommunicationConfig {
+            dimensions: dimensions,
+            range: config["signal_range"].as_f64().unwrap() as f32,
+            emit: species["emit"].as_bool().unwrap(),
+            hears: species["hears"].as_array().unwrap().iter().map(|name| species_id(name.as_str().unwrap())).collect(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ReproductionMode {
+    // An agent with enough energy gives birth alone to a mutated copy of itself
+    Asexual,
+    // Two agents with enough energy within the mating radius have an offspring with a crossover of their genomes
+    Mating,
+}
+
+impl ReproductionMode {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "asexual" => ReproductionMode::Asexual,
+            "mating" => ReproductionMode::Mating,
+            _ => panic!("Unknown reproduction mode: {}", name),
+        }
+    }
+}
+
+// Reproduction in the ecological evolution mode
+#[derive(Clone)]
+pub struct ReproductionConfig {
+    pub mode: ReproductionMode,
+    // An agent with at least this much energy can give birth, the offspring gets half of the energy
+    // of a single parent or a quarter of the energy of each of two parents
+    pub energy_threshold: i32,
+    // No births while this many agents of the species are alive
+    pub max_population: usize,
+    pub mating_radius: f32,
+}
+
+impl ReproductionConfig {
+    pub fn from_config(config: &Value) -> Self {
+        ReproductionConfig {
+            mode: ReproductionMode::from_name(config["mode"].as_str().unwrap()),
+            energy_threshold: config["energy_threshold"].as_i64().unwrap() as i32,
+            max_population: config["max_population"].as_u64().unwrap() as usize,
+            mating_radius: config["mating_radius"].as_f64().unwrap() as f32,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SpeciesConfig {
+    pub sensors: SensorConfig,
+    pub communication: CommunicationConfig,
+    pub selection: Selection,
+    pub placement: Placement,
+    pub fitness: Fitness,
+    pub reproduction: ReproductionConfig,
+    pub sharing: Sharing,
+    // Used when the species is the prey
+    pub capture: Capture,
+}
+
+impl SpeciesConfig {
+    pub fn from_config(config: &Value, wall_config: &Value, name: &str) -> Self {
+        let species = &config["species"][name];
+        SpeciesConfig {
+            sensors: SensorConfig::from_config(&species["sensors"]),
+            communication: CommunicationConfig::from_config(config, &species["communication"]),
+            selection: Selection::from_config(&species["selection"]),
+            placement: Placement::from_config(config, wall_config, &species["placement"], name),
+            fitness: Fitness::from_config(&species["fitness"]),
+            reproduction: ReproductionConfig::from_config(&species["reproduction"]),
+            sharing: Sharing::from_config(config, &species["sharing"], name),
+            capture: Capture::from_config(&species["capture"]),
+        }
+    }
+}